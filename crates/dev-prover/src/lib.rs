@@ -0,0 +1,405 @@
+//! A prover for local development and testing that speaks the `semaphore-mtb`
+//! protocol.
+//!
+//! Instead of generating a Groth16 proof, the dev prover checks that the
+//! Merkle tree transition it's asked to prove is valid, and returns a proof
+//! derived from the input hash. The proof is only accepted by verifiers that
+//! don't check it, such as the `SequencerVerifier` used in the tests.
+
+use ethers::types::U256;
+use ethers::utils::keccak256;
+use semaphore::merkle_tree::Hasher;
+use semaphore::poseidon_tree::PoseidonHash;
+use semaphore::Field;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+pub mod server;
+
+pub use self::server::{spawn, ServerHandle};
+
+/// The modulus of the base field of BN254, which the coordinates of the proof
+/// points must be smaller than.
+const BASE_FIELD_MODULUS: &str = "30644e72e131a029b85045b68181585d97816a916871ca8d3c208c16d87cfd47";
+
+/// Whether the prover generates insertion or deletion proofs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "camelCase")]
+pub enum ProverType {
+    Insertion,
+    Deletion,
+}
+
+/// The circuit parameters served by the `info` endpoint.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProverInfo {
+    pub batch_size:  usize,
+    pub tree_depth:  usize,
+    pub prover_type: ProverType,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Proof {
+    pub ar:  [U256; 2],
+    pub bs:  [[U256; 2]; 2],
+    pub krs: [U256; 2],
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InsertionProofInput {
+    pub input_hash:           U256,
+    pub start_index:          u32,
+    pub pre_root:             U256,
+    pub post_root:            U256,
+    pub identity_commitments: Vec<U256>,
+    pub merkle_proofs:        Vec<Vec<U256>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeletionProofInput {
+    pub input_hash:           U256,
+    pub pre_root:             U256,
+    pub post_root:            U256,
+    pub deletion_indices:     Vec<u32>,
+    pub identity_commitments: Vec<U256>,
+    pub merkle_proofs:        Vec<Vec<U256>>,
+}
+
+/// Why the inputs of a proof were rejected.
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub enum InvalidInput {
+    #[error("expected {expected} identities, got {actual}")]
+    BatchSize { expected: usize, actual: usize },
+    #[error("merkle proof {index} has {actual} nodes instead of {expected}")]
+    ProofLength {
+        index:    usize,
+        expected: usize,
+        actual:   usize,
+    },
+    #[error("the input hash does not match the inputs")]
+    InputHash,
+    #[error("leaf {index} does not hold the expected value under the current root")]
+    Leaf { index: u32 },
+    #[error("the post root does not match the updated tree")]
+    PostRoot,
+}
+
+impl InvalidInput {
+    /// The error code reported to the sequencer.
+    #[must_use]
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::BatchSize { .. } | Self::ProofLength { .. } => "invalid_batch",
+            Self::InputHash => "invalid_input_hash",
+            Self::Leaf { .. } | Self::PostRoot => "invalid_transition",
+        }
+    }
+}
+
+/// Validates the circuit inputs the same way the circuits constrain them.
+#[derive(Clone, Debug)]
+pub struct DevProver {
+    info: ProverInfo,
+}
+
+impl DevProver {
+    #[must_use]
+    pub fn new(info: ProverInfo) -> Self {
+        Self { info }
+    }
+
+    #[must_use]
+    pub fn info(&self) -> &ProverInfo {
+        &self.info
+    }
+
+    /// Proves that the identities were inserted at consecutive indices from
+    /// `start_index`, starting from empty leaves.
+    pub fn prove_insertion(&self, input: &InsertionProofInput) -> Result<Proof, InvalidInput> {
+        self.check_batch(&input.identity_commitments, &input.merkle_proofs)?;
+
+        let input_hash = insertion_input_hash(
+            input.start_index,
+            input.pre_root,
+            input.post_root,
+            &input.identity_commitments,
+        );
+        if input_hash != input.input_hash {
+            return Err(InvalidInput::InputHash);
+        }
+
+        let mut root = input.pre_root;
+        for ((offset, commitment), proof) in (0u32..)
+            .zip(&input.identity_commitments)
+            .zip(&input.merkle_proofs)
+        {
+            let index = input.start_index + offset;
+            if compute_root(index, U256::zero(), proof) != root {
+                return Err(InvalidInput::Leaf { index });
+            }
+            root = compute_root(index, *commitment, proof);
+        }
+
+        if root != input.post_root {
+            return Err(InvalidInput::PostRoot);
+        }
+
+        Ok(deterministic_proof(input_hash))
+    }
+
+    /// Proves that the identities at the deletion indices were replaced with
+    /// empty leaves. Indices outside of the tree are padding, and skipped.
+    pub fn prove_deletion(&self, input: &DeletionProofInput) -> Result<Proof, InvalidInput> {
+        self.check_batch(&input.identity_commitments, &input.merkle_proofs)?;
+        if input.deletion_indices.len() != self.info.batch_size {
+            return Err(InvalidInput::BatchSize {
+                expected: self.info.batch_size,
+                actual:   input.deletion_indices.len(),
+            });
+        }
+
+        let input_hash =
+            deletion_input_hash(&input.deletion_indices, input.pre_root, input.post_root);
+        if input_hash != input.input_hash {
+            return Err(InvalidInput::InputHash);
+        }
+
+        let capacity = 1_u64 << self.info.tree_depth;
+        let mut root = input.pre_root;
+        for ((&index, commitment), proof) in input
+            .deletion_indices
+            .iter()
+            .zip(&input.identity_commitments)
+            .zip(&input.merkle_proofs)
+        {
+            if u64::from(index) >= capacity {
+                continue;
+            }
+            if compute_root(index, *commitment, proof) != root {
+                return Err(InvalidInput::Leaf { index });
+            }
+            root = compute_root(index, U256::zero(), proof);
+        }
+
+        if root != input.post_root {
+            return Err(InvalidInput::PostRoot);
+        }
+
+        Ok(deterministic_proof(input_hash))
+    }
+
+    fn check_batch(
+        &self,
+        identity_commitments: &[U256],
+        merkle_proofs: &[Vec<U256>],
+    ) -> Result<(), InvalidInput> {
+        let expected = self.info.batch_size;
+        for actual in [identity_commitments.len(), merkle_proofs.len()] {
+            if actual != expected {
+                return Err(InvalidInput::BatchSize { expected, actual });
+            }
+        }
+
+        let expected = self.info.tree_depth;
+        for (index, proof) in merkle_proofs.iter().enumerate() {
+            if proof.len() != expected {
+                return Err(InvalidInput::ProofLength {
+                    index,
+                    expected,
+                    actual: proof.len(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Computes the root of the tree with `leaf` at `index`, given the siblings of
+/// the nodes on the path from the leaf to the root.
+fn compute_root(index: u32, leaf: U256, siblings: &[U256]) -> U256 {
+    let mut node: Field = leaf.into();
+
+    for (depth, sibling) in siblings.iter().enumerate() {
+        let sibling: Field = (*sibling).into();
+        node = if (u64::from(index) >> depth) & 1 == 0 {
+            PoseidonHash::hash_node(&node, &sibling)
+        } else {
+            PoseidonHash::hash_node(&sibling, &node)
+        };
+    }
+
+    node.into()
+}
+
+/// The input hash of an insertion, as computed by the sequencer and the
+/// identity manager contract.
+fn insertion_input_hash(
+    start_index: u32,
+    pre_root: U256,
+    post_root: U256,
+    identity_commitments: &[U256],
+) -> U256 {
+    let mut bytes = start_index.to_be_bytes().to_vec();
+    for value in [pre_root, post_root]
+        .iter()
+        .chain(identity_commitments.iter())
+    {
+        bytes.extend(u256_to_bytes(*value));
+    }
+
+    keccak256(bytes).into()
+}
+
+/// The input hash of a deletion, as computed by the sequencer and the identity
+/// manager contract.
+fn deletion_input_hash(deletion_indices: &[u32], pre_root: U256, post_root: U256) -> U256 {
+    let mut bytes: Vec<u8> = deletion_indices
+        .iter()
+        .flat_map(|index| index.to_be_bytes())
+        .collect();
+    bytes.extend(u256_to_bytes(pre_root));
+    bytes.extend(u256_to_bytes(post_root));
+
+    keccak256(bytes).into()
+}
+
+/// A proof whose coordinates are derived from the `input_hash`, so that the
+/// same inputs always produce the same proof.
+fn deterministic_proof(input_hash: U256) -> Proof {
+    let modulus = U256::from_str_radix(BASE_FIELD_MODULUS, 16).expect("modulus is valid hex");
+    let mut seed = u256_to_bytes(input_hash).to_vec();
+    seed.push(0);
+
+    let mut words = [U256::zero(); 8];
+    for (counter, word) in (0u8..).zip(words.iter_mut()) {
+        *seed.last_mut().expect("seed is not empty") = counter;
+        *word = U256::from(keccak256(&seed)) % modulus;
+    }
+
+    Proof {
+        ar:  [words[0], words[1]],
+        bs:  [[words[2], words[3]], [words[4], words[5]]],
+        krs: [words[6], words[7]],
+    }
+}
+
+fn u256_to_bytes(value: U256) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use semaphore::lazy_merkle_tree::{Canonical, LazyMerkleTree};
+    use semaphore::merkle_tree::Branch;
+
+    use super::*;
+
+    const TREE_DEPTH: usize = 4;
+
+    type Tree = LazyMerkleTree<PoseidonHash, Canonical>;
+
+    fn siblings(tree: &Tree, index: usize) -> Vec<U256> {
+        tree.proof(index)
+            .0
+            .iter()
+            .map(|branch| match branch {
+                Branch::Left(value) | Branch::Right(value) => (*value).into(),
+            })
+            .collect()
+    }
+
+    fn prover(prover_type: ProverType) -> DevProver {
+        DevProver::new(ProverInfo {
+            batch_size: 2,
+            tree_depth: TREE_DEPTH,
+            prover_type,
+        })
+    }
+
+    fn insertion() -> (Tree, InsertionProofInput) {
+        let mut tree = Tree::new(TREE_DEPTH, Field::ZERO);
+        let pre_root: U256 = tree.root().into();
+
+        let identity_commitments = vec![U256::from(1), U256::from(2)];
+        let mut merkle_proofs = vec![];
+        for (index, commitment) in identity_commitments.iter().enumerate() {
+            tree = tree.update(index, &(*commitment).into());
+            merkle_proofs.push(siblings(&tree, index));
+        }
+
+        let post_root: U256 = tree.root().into();
+        let input = InsertionProofInput {
+            input_hash: insertion_input_hash(0, pre_root, post_root, &identity_commitments),
+            start_index: 0,
+            pre_root,
+            post_root,
+            identity_commitments,
+            merkle_proofs,
+        };
+
+        (tree, input)
+    }
+
+    #[test]
+    fn proves_valid_insertions() {
+        let (_, input) = insertion();
+        let prover = prover(ProverType::Insertion);
+
+        let proof = prover.prove_insertion(&input).unwrap();
+        assert_eq!(prover.prove_insertion(&input).unwrap(), proof);
+    }
+
+    #[test]
+    fn rejects_invalid_insertions() {
+        let (_, mut input) = insertion();
+        let prover = prover(ProverType::Insertion);
+
+        input.post_root = U256::from(42);
+        input.input_hash = insertion_input_hash(
+            input.start_index,
+            input.pre_root,
+            input.post_root,
+            &input.identity_commitments,
+        );
+        assert_eq!(prover.prove_insertion(&input), Err(InvalidInput::PostRoot));
+
+        input.input_hash = U256::zero();
+        assert_eq!(prover.prove_insertion(&input), Err(InvalidInput::InputHash));
+    }
+
+    #[test]
+    fn proves_valid_deletions() {
+        let (mut tree, insertion) = insertion();
+        let pre_root: U256 = tree.root().into();
+
+        tree = tree.update(1, &Field::ZERO);
+        let post_root: U256 = tree.root().into();
+
+        // The second deletion is padding outside of the tree
+        let deletion_indices = vec![1, 1 << TREE_DEPTH];
+        let input = DeletionProofInput {
+            input_hash: deletion_input_hash(&deletion_indices, pre_root, post_root),
+            pre_root,
+            post_root,
+            deletion_indices,
+            identity_commitments: vec![insertion.identity_commitments[1], U256::zero()],
+            merkle_proofs: vec![siblings(&tree, 1), vec![U256::zero(); TREE_DEPTH]],
+        };
+
+        let prover = prover(ProverType::Deletion);
+        assert!(prover.prove_deletion(&input).is_ok());
+
+        let mut wrong_commitment = input;
+        wrong_commitment.identity_commitments[0] = U256::from(3);
+        assert_eq!(
+            prover.prove_deletion(&wrong_commitment),
+            Err(InvalidInput::Leaf { index: 1 })
+        );
+    }
+}
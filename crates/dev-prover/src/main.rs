@@ -0,0 +1,43 @@
+use std::net::SocketAddr;
+
+use clap::Parser;
+use dev_prover::{ProverInfo, ProverType};
+
+/// Serves a dev prover, which validates the tree transitions it's asked to
+/// prove and returns deterministic proofs instead of real ones.
+#[derive(Debug, Parser)]
+struct Args {
+    /// The address to listen on.
+    #[clap(long, env, default_value = "127.0.0.1:3001")]
+    addr: SocketAddr,
+
+    /// The number of identities in each batch.
+    #[clap(long, env, default_value = "3")]
+    batch_size: usize,
+
+    /// The depth of the identity tree.
+    #[clap(long, env, default_value = "30")]
+    tree_depth: usize,
+
+    /// Whether to prove insertions or deletions.
+    #[clap(long, env, value_enum, default_value = "insertion")]
+    prover_type: ProverType,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt::init();
+
+    let args = Args::parse();
+
+    let handle = dev_prover::spawn(args.addr, ProverInfo {
+        batch_size:  args.batch_size,
+        tree_depth:  args.tree_depth,
+        prover_type: args.prover_type,
+    })
+    .await?;
+
+    tracing::info!(endpoint = handle.endpoint(), "Dev prover started");
+
+    handle.join().await
+}
@@ -0,0 +1,141 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use anyhow::Context;
+use axum::extract::State;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+
+use crate::{DevProver, InvalidInput, Proof, ProverInfo, ProverType};
+
+/// The error reported to the sequencer when the inputs are rejected, in the
+/// format used by `semaphore-mtb`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ProverError {
+    code:    String,
+    message: String,
+}
+
+impl From<InvalidInput> for ProverError {
+    fn from(error: InvalidInput) -> Self {
+        Self {
+            code:    error.code().into(),
+            message: error.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+#[allow(clippy::large_enum_variant)]
+enum ProveResponse {
+    ProofSuccess(Proof),
+    ProofFailure(ProverError),
+}
+
+impl From<Result<Proof, InvalidInput>> for ProveResponse {
+    fn from(result: Result<Proof, InvalidInput>) -> Self {
+        match result {
+            Ok(proof) => Self::ProofSuccess(proof),
+            Err(error) => {
+                tracing::warn!(%error, "Rejected proof inputs");
+
+                Self::ProofFailure(error.into())
+            }
+        }
+    }
+}
+
+async fn prove(
+    State(prover): State<Arc<DevProver>>,
+    Json(input): Json<serde_json::Value>,
+) -> Result<Json<ProveResponse>, (hyper::StatusCode, String)> {
+    let bad_request =
+        |error: serde_json::Error| (hyper::StatusCode::BAD_REQUEST, error.to_string());
+
+    // Rejected inputs are reported in the body of a successful response, the
+    // same way `semaphore-mtb` reports them
+    let response: ProveResponse = match prover.info().prover_type {
+        ProverType::Insertion => {
+            let input = serde_json::from_value(input).map_err(bad_request)?;
+            prover.prove_insertion(&input).into()
+        }
+        ProverType::Deletion => {
+            let input = serde_json::from_value(input).map_err(bad_request)?;
+            prover.prove_deletion(&input).into()
+        }
+    };
+
+    Ok(Json(response))
+}
+
+async fn info(State(prover): State<Arc<DevProver>>) -> Json<ProverInfo> {
+    Json(prover.info().clone())
+}
+
+pub struct ServerHandle {
+    addr:               SocketAddr,
+    shutdown_notify:    Arc<Notify>,
+    server_join_handle: JoinHandle<Result<(), hyper::Error>>,
+}
+
+impl ServerHandle {
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn endpoint(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    pub async fn shutdown(self) {
+        self.shutdown_notify.notify_waiters();
+
+        if let Err(e) = self.server_join_handle.await {
+            tracing::error!("Server error: {:?}", e);
+        }
+    }
+
+    /// Waits until the server stops.
+    pub async fn join(self) -> anyhow::Result<()> {
+        self.server_join_handle.await??;
+
+        Ok(())
+    }
+}
+
+/// Serves a dev prover with the given circuit parameters at `addr`. Use port 0
+/// to bind a random port.
+pub async fn spawn(addr: SocketAddr, info: ProverInfo) -> anyhow::Result<ServerHandle> {
+    let prover = Arc::new(DevProver::new(info));
+
+    let router = Router::new()
+        .route("/prove", post(prove))
+        .route("/info", get(info))
+        .with_state(prover);
+
+    let listener = std::net::TcpListener::bind(addr).context("Failed to bind address")?;
+    let local_addr = listener.local_addr()?;
+
+    let shutdown_notify = Arc::new(Notify::new());
+
+    let server = axum::Server::from_tcp(listener)?
+        .serve(router.into_make_service())
+        .with_graceful_shutdown({
+            let shutdown_notify = shutdown_notify.clone();
+            async move {
+                shutdown_notify.notified().await;
+            }
+        });
+
+    let server_join_handle = tokio::spawn(server);
+
+    Ok(ServerHandle {
+        addr: local_addr,
+        shutdown_notify,
+        server_join_handle,
+    })
+}
@@ -0,0 +1,49 @@
+//! A minimal stand-in for a remote signer such as Web3Signer, for tests.
+//!
+//! It holds a single key and implements the JSON-RPC methods the remote write
+//! provider uses.
+
+use anyhow::anyhow;
+use ethers::prelude::k256::ecdsa::SigningKey;
+use ethers::signers::{LocalWallet, Signer};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{Address, Bytes};
+
+pub mod server;
+
+pub use self::server::{spawn, ServerHandle};
+
+#[derive(Clone, Debug)]
+pub struct MicroSigner {
+    wallet: LocalWallet,
+}
+
+impl MicroSigner {
+    #[must_use]
+    pub fn new(secret_key: SigningKey) -> Self {
+        Self {
+            wallet: LocalWallet::from(secret_key),
+        }
+    }
+
+    #[must_use]
+    pub fn address(&self) -> Address {
+        self.wallet.address()
+    }
+
+    /// Signs the transaction like `eth_signTransaction`, returning the raw
+    /// signed transaction.
+    pub async fn sign_transaction(&self, tx: &TypedTransaction) -> anyhow::Result<Bytes> {
+        if tx.from() != Some(&self.address()) {
+            return Err(anyhow!("Unknown account {:?}", tx.from()));
+        }
+
+        if tx.chain_id().is_none() {
+            return Err(anyhow!("Missing chain id"));
+        }
+
+        let signature = self.wallet.sign_transaction(tx).await?;
+
+        Ok(tx.rlp_signed(&signature))
+    }
+}
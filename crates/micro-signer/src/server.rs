@@ -0,0 +1,149 @@
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener};
+use std::sync::Arc;
+
+use anyhow::Context;
+use axum::extract::State;
+use axum::routing::post;
+use axum::{Json, Router};
+use ethers::prelude::k256::ecdsa::SigningKey;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::Address;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+
+use crate::MicroSigner;
+
+/// JSON-RPC error code for invalid method parameters.
+const INVALID_PARAMS: i64 = -32602;
+/// JSON-RPC error code for unknown methods.
+const METHOD_NOT_FOUND: i64 = -32601;
+/// JSON-RPC error code for failures of the signer itself.
+const SERVER_ERROR: i64 = -32000;
+
+#[derive(Debug, Deserialize)]
+struct JsonRpcRequest {
+    id:     Value,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonRpcError {
+    code:    i64,
+    message: String,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonRpcResponse {
+    jsonrpc: &'static str,
+    id:      Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result:  Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error:   Option<JsonRpcError>,
+}
+
+async fn rpc(
+    State(signer): State<MicroSigner>,
+    Json(request): Json<JsonRpcRequest>,
+) -> Json<JsonRpcResponse> {
+    let result = handle(&signer, &request.method, request.params).await;
+
+    let (result, error) = match result {
+        Ok(result) => (Some(result), None),
+        Err((code, message)) => {
+            tracing::error!("MicroSigner {} error: {}", request.method, message);
+
+            (None, Some(JsonRpcError { code, message }))
+        }
+    };
+
+    Json(JsonRpcResponse {
+        jsonrpc: "2.0",
+        id: request.id,
+        result,
+        error,
+    })
+}
+
+async fn handle(signer: &MicroSigner, method: &str, params: Value) -> Result<Value, (i64, String)> {
+    match method {
+        "eth_accounts" => Ok(serde_json::json!([signer.address()])),
+        "eth_signTransaction" => {
+            let (tx,): (TypedTransaction,) = serde_json::from_value(params)
+                .map_err(|error| (INVALID_PARAMS, error.to_string()))?;
+
+            let signed = signer
+                .sign_transaction(&tx)
+                .await
+                .map_err(|error| (SERVER_ERROR, error.to_string()))?;
+
+            Ok(serde_json::json!(signed))
+        }
+        _ => Err((METHOD_NOT_FOUND, format!("Unsupported method {method}"))),
+    }
+}
+
+pub struct ServerHandle {
+    signer:             MicroSigner,
+    addr:               SocketAddr,
+    shutdown_notify:    Arc<Notify>,
+    server_join_handle: JoinHandle<Result<(), hyper::Error>>,
+}
+
+impl ServerHandle {
+    pub fn address(&self) -> Address {
+        self.signer.address()
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn endpoint(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    pub async fn shutdown(self) {
+        self.shutdown_notify.notify_waiters();
+
+        if let Err(e) = self.server_join_handle.await {
+            tracing::error!("Server error: {:?}", e);
+        }
+    }
+}
+
+pub async fn spawn(secret_key: SigningKey) -> anyhow::Result<ServerHandle> {
+    let signer = MicroSigner::new(secret_key);
+
+    let router = Router::new()
+        .route("/", post(rpc))
+        .with_state(signer.clone());
+
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0);
+    let listener = TcpListener::bind(addr).context("Failed to bind random port")?;
+    let local_addr = listener.local_addr()?;
+
+    let shutdown_notify = Arc::new(Notify::new());
+
+    let server = axum::Server::from_tcp(listener)?
+        .serve(router.into_make_service())
+        .with_graceful_shutdown({
+            let shutdown_notify = shutdown_notify.clone();
+            async move {
+                shutdown_notify.notified().await;
+            }
+        });
+
+    let server_join_handle = tokio::spawn(server);
+
+    Ok(ServerHandle {
+        signer,
+        addr: local_addr,
+        shutdown_notify,
+        server_join_handle,
+    })
+}
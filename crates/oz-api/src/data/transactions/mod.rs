@@ -44,13 +44,17 @@ impl fmt::Display for Status {
 #[serde(rename_all = "camelCase")]
 pub struct SendBaseTransactionRequest<'a> {
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub to:          Option<&'a NameOrAddress>,
+    pub to: Option<&'a NameOrAddress>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub value:       Option<&'a U256>,
+    pub value: Option<&'a U256>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub data:        Option<&'a Bytes>,
+    pub data: Option<&'a Bytes>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub gas_limit:   Option<&'a U256>,
+    pub gas_limit: Option<&'a U256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_fee_per_gas: Option<&'a U256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_priority_fee_per_gas: Option<&'a U256>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub valid_until: Option<DateTime<Utc>>,
 }
@@ -63,16 +67,22 @@ pub struct SendBaseTransactionRequest<'a> {
 pub struct SendBaseTransactionRequestOwned {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
-    pub to:          Option<NameOrAddress>,
+    pub to: Option<NameOrAddress>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub value: Option<U256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub data: Option<Bytes>,
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
-    pub value:       Option<U256>,
+    pub gas_limit: Option<U256>,
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
-    pub data:        Option<Bytes>,
+    pub max_fee_per_gas: Option<U256>,
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
-    pub gas_limit:   Option<U256>,
+    pub max_priority_fee_per_gas: Option<U256>,
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
     pub valid_until: Option<DateTime<Utc>>,
@@ -0,0 +1,4 @@
+CREATE TABLE paused_tasks (
+    task        VARCHAR(50) NOT NULL PRIMARY KEY,
+    paused_at   TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
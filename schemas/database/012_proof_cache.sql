@@ -0,0 +1,5 @@
+CREATE TABLE proof_cache (
+    input_hash  BYTEA NOT NULL PRIMARY KEY,
+    proof       BYTEA NOT NULL,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
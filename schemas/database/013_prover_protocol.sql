@@ -0,0 +1,4 @@
+CREATE TYPE prover_protocol_enum AS ENUM('Sync', 'Async');
+
+-- Existing provers all use the synchronous protocol
+ALTER TABLE provers ADD COLUMN protocol prover_protocol_enum NOT NULL DEFAULT 'Sync';
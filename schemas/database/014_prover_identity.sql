@@ -0,0 +1,10 @@
+-- Provers are identified by their batch size, type and URL. Remove any
+-- duplicates before enforcing that.
+DELETE FROM provers a
+USING provers b
+WHERE a.ctid < b.ctid
+  AND a.batch_size = b.batch_size
+  AND a.prover_type = b.prover_type
+  AND a.url = b.url;
+
+ALTER TABLE provers ADD CONSTRAINT provers_pkey PRIMARY KEY (batch_size, prover_type, url);
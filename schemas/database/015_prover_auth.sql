@@ -0,0 +1,3 @@
+-- The authentication settings of a prover as JSON, or NULL if requests to the
+-- prover aren't authenticated
+ALTER TABLE provers ADD COLUMN auth TEXT;
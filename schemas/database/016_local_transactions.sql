@@ -0,0 +1,20 @@
+CREATE TYPE local_transaction_status_enum AS ENUM('Pending', 'Mined', 'Failed');
+
+-- Transactions signed and sent by the local signer write provider
+CREATE TABLE local_transactions (
+    id          TEXT NOT NULL PRIMARY KEY,
+    signer      BYTEA NOT NULL,
+    nonce       BIGINT NOT NULL,
+    -- The keccak256 hash of the calldata, to find transactions that were
+    -- already sent
+    data_hash   BYTEA NOT NULL,
+    -- The latest version of the unsigned transaction, as JSON
+    tx          TEXT NOT NULL,
+    -- The hashes of every version of the transaction that was sent, as any of
+    -- them can end up being mined
+    tx_hashes   BYTEA[] NOT NULL,
+    status      local_transaction_status_enum NOT NULL DEFAULT 'Pending',
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    sent_at     TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (signer, nonce)
+);
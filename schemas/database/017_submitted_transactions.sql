@@ -0,0 +1,17 @@
+CREATE TYPE submitted_transaction_status_enum AS ENUM('Pending', 'Mined', 'Failed');
+
+-- Batches sent on chain, recorded so that they can be monitored again after a
+-- restart regardless of the write provider. Batches are recorded before they
+-- are sent.
+CREATE TABLE submitted_transactions (
+    id              BIGSERIAL PRIMARY KEY,
+    -- The id of the transaction with the write provider, or NULL if the batch
+    -- wasn't handed to it yet
+    transaction_id  TEXT UNIQUE,
+    -- The keccak256 hash of the calldata
+    calldata_hash   BYTEA NOT NULL,
+    pre_root        BYTEA NOT NULL,
+    post_root       BYTEA NOT NULL,
+    status          submitted_transaction_status_enum NOT NULL DEFAULT 'Pending',
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
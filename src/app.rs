@@ -3,7 +3,7 @@ use std::sync::Arc;
 use std::time::Instant;
 
 use anyhow::Result as AnyhowResult;
-use chrono::{Duration, Utc};
+use chrono::{DateTime, Duration, Utc};
 use clap::Parser;
 use hyper::StatusCode;
 use ruint::Uint;
@@ -19,9 +19,12 @@ use crate::identity_tree::{
     CanonicalTreeBuilder, Hash, InclusionProof, RootItem, Status, TreeState, TreeVersionReadOps,
 };
 use crate::prover::map::initialize_prover_maps;
-use crate::prover::{self, ProverConfiguration, ProverType, Provers};
+use crate::prover::{
+    self, ProofVerifiers, ProverAuth, ProverConfiguration, ProverProtocol, ProverType, Provers,
+};
 use crate::server::error::Error as ServerError;
 use crate::server::{ToResponseCode, VerifySemaphoreProofQuery, VerifySemaphoreProofRequest};
+use crate::task_monitor::health::{TaskHealthReport, TaskKind};
 use crate::task_monitor::TaskMonitor;
 use crate::utils::tree_updates::dedup_tree_updates;
 use crate::{contracts, task_monitor};
@@ -75,6 +78,42 @@ impl ToResponseCode for ListBatchSizesResponse {
     }
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InsertionStatusResponse {
+    pub status:      Status,
+    /// The time at which the identity was submitted. Only available while the
+    /// identity has not yet been added to the tree.
+    pub created_at:  Option<DateTime<Utc>>,
+    /// The time after which the identity will be inserted into the tree. Only
+    /// available while the identity has not yet been added to the tree.
+    pub eligible_at: Option<DateTime<Utc>>,
+    pub message:     Option<String>,
+}
+
+impl ToResponseCode for InsertionStatusResponse {
+    fn to_response_code(&self) -> StatusCode {
+        StatusCode::OK
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthResponse {
+    pub healthy: bool,
+    pub tasks:   Vec<TaskHealthReport>,
+}
+
+impl ToResponseCode for HealthResponse {
+    fn to_response_code(&self) -> StatusCode {
+        if self.healthy {
+            StatusCode::OK
+        } else {
+            StatusCode::SERVICE_UNAVAILABLE
+        }
+    }
+}
+
 #[derive(Serialize)]
 #[serde(transparent)]
 pub struct VerifySemaphoreProofResponse(RootItem);
@@ -148,32 +187,36 @@ impl App {
     /// `options.storage_file` is not accessible.
     #[instrument(name = "App::new", level = "debug")]
     pub async fn new(options: Options) -> AnyhowResult<Self> {
-        let ethereum = Ethereum::new(options.ethereum);
-        let db = Database::new(options.database);
-
-        let (ethereum, db) = tokio::try_join!(ethereum, db)?;
+        // The database is needed by the local signer write provider
+        let database = Arc::new(Database::new(options.database).await?);
+        let ethereum = Ethereum::new(options.ethereum, database.clone()).await?;
 
-        let database = Arc::new(db);
         let mut provers: HashSet<ProverConfiguration> = database.get_provers().await?;
 
+        let verifiers = ProofVerifiers::load(&options.batch_provers.verifying_keys.0)?;
+
         let non_inserted_provers = Self::merge_env_provers(options.batch_provers, &mut provers);
 
         database.insert_provers(non_inserted_provers).await?;
 
-        let (insertion_prover_map, deletion_prover_map) = initialize_prover_maps(provers)?;
+        let (insertion_prover_map, deletion_prover_map) =
+            initialize_prover_maps(provers, &verifiers)?;
 
         let identity_manager = IdentityManager::new(
             options.contracts,
             ethereum.clone(),
             insertion_prover_map,
             deletion_prover_map,
+            verifiers,
         )
         .await?;
 
         let identity_manager = Arc::new(identity_manager);
 
+        IdentityManager::prune_proof_cache(&database).await;
+
         // Await for all pending transactions
-        identity_manager.await_clean_slate().await?;
+        identity_manager.await_clean_slate(&database).await?;
 
         // Prefetch latest root & mark it as mined
         let root_hash = identity_manager.latest_root().await?;
@@ -221,7 +264,7 @@ impl App {
         .expect("This should just parse.");
 
         // Process to push new identities to Ethereum
-        identity_committer.start().await;
+        identity_committer.start().await?;
 
         // Sync with chain on start up
         let app = Self {
@@ -293,12 +336,20 @@ impl App {
 
     /// Queues an insert into the merkle tree.
     ///
+    /// If `not_before` is provided the identity will not be inserted into the
+    /// tree until that time has passed. Until then the insertion can be
+    /// cancelled with [`Self::cancel_scheduled_insertion`].
+    ///
     /// # Errors
     ///
     /// Will return `Err` if identity is already queued, or in the tree, or the
     /// queue malfunctions.
     #[instrument(level = "debug", skip(self))]
-    pub async fn insert_identity(&self, commitment: Hash) -> Result<(), ServerError> {
+    pub async fn insert_identity(
+        &self,
+        commitment: Hash,
+        not_before: Option<DateTime<Utc>>,
+    ) -> Result<(), ServerError> {
         if commitment == self.identity_manager.initial_leaf_value() {
             warn!(?commitment, "Attempt to insert initial leaf.");
             return Err(ServerError::InvalidCommitment);
@@ -326,13 +377,119 @@ impl App {
             return Err(ServerError::DuplicateCommitment);
         }
 
+        let eligibility_timestamp = not_before.unwrap_or_else(Utc::now);
+
         self.database
-            .insert_new_identity(commitment, Utc::now())
+            .insert_new_identity(commitment, eligibility_timestamp)
             .await?;
 
+        self.identity_committer.notify_queued_insertion();
+
         Ok(())
     }
 
+    /// Cancels an insertion that was scheduled with a `not_before` time that
+    /// has not yet passed.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the identity is unknown, if it has already become
+    /// eligible for insertion or if the database malfunctions.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn cancel_scheduled_insertion(&self, commitment: &Hash) -> Result<(), ServerError> {
+        if self
+            .database
+            .remove_scheduled_unprocessed_identity(commitment)
+            .await?
+        {
+            return Ok(());
+        }
+
+        if self.database.identity_exists(*commitment).await? {
+            Err(ServerError::InsertionNotScheduled)
+        } else {
+            Err(ServerError::IdentityCommitmentNotFound)
+        }
+    }
+
+    /// Cancels a queued insertion, as long as the identity has not yet been
+    /// added to the latest tree.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the identity is unknown, if it has already been
+    /// inserted into the tree or if the database malfunctions.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn cancel_insertion(&self, commitment: &Hash) -> Result<(), ServerError> {
+        // Prevent the identity from being moved into the tree while we remove it
+        let _queues_guard = self.identity_committer.lock_queues().await;
+
+        if self
+            .database
+            .get_unprocessed_commitment(commitment)
+            .await?
+            .is_some()
+        {
+            self.database
+                .remove_unprocessed_identity(commitment)
+                .await?;
+            return Ok(());
+        }
+
+        if self
+            .database
+            .get_identity_leaf_index(commitment)
+            .await?
+            .is_some()
+        {
+            Err(ServerError::IdentityAlreadyInserted)
+        } else {
+            Err(ServerError::IdentityCommitmentNotFound)
+        }
+    }
+
+    /// Returns the status of an identity insertion, including the time it is
+    /// scheduled to be inserted at if it has not been added to the tree yet.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the identity is unknown or if the database
+    /// malfunctions.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn insertion_status(
+        &self,
+        commitment: &Hash,
+    ) -> Result<InsertionStatusResponse, ServerError> {
+        if let Some(unprocessed) = self.database.get_unprocessed_commitment(commitment).await? {
+            return Ok(InsertionStatusResponse {
+                status:      unprocessed.status,
+                created_at:  Some(unprocessed.created_at),
+                eligible_at: Some(unprocessed.eligibility_timestamp),
+                message:     unprocessed.error_message,
+            });
+        }
+
+        let item = self
+            .database
+            .get_identity_leaf_index(commitment)
+            .await?
+            .ok_or(ServerError::IdentityCommitmentNotFound)?;
+
+        // The processed status is hidden from API users
+        let status = if item.status == Status::Processed {
+            Status::Pending
+        } else {
+            item.status
+        };
+
+        Ok(InsertionStatusResponse {
+            status,
+            created_at: None,
+            eligible_at: None,
+            message: None,
+        })
+    }
+
     /// Queues a deletion from the merkle tree.
     ///
     /// # Errors
@@ -388,9 +545,46 @@ impl App {
             .insert_new_deletion(leaf_index, commitment)
             .await?;
 
+        self.identity_committer.notify_queued_deletion();
+
         Ok(())
     }
 
+    /// Cancels a queued deletion, as long as it has not yet been applied to
+    /// the latest tree. A recovery of the identity is cancelled along with it.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the identity is unknown, not queued for deletion,
+    /// already deleted or if the database malfunctions.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn cancel_deletion(&self, commitment: &Hash) -> Result<(), ServerError> {
+        // Prevent the deletion from being applied to the tree while we remove it
+        let _queues_guard = self.identity_committer.lock_queues().await;
+
+        if self
+            .database
+            .identity_is_queued_for_deletion(commitment)
+            .await?
+        {
+            self.database.cancel_deletion(commitment).await?;
+            return Ok(());
+        }
+
+        let leaf_index = self
+            .database
+            .get_identity_leaf_index(commitment)
+            .await?
+            .ok_or(ServerError::IdentityCommitmentNotFound)?
+            .leaf_index;
+
+        if self.tree_state.get_latest_tree().get_leaf(leaf_index) == Uint::ZERO {
+            Err(ServerError::IdentityAlreadyDeleted)
+        } else {
+            Err(ServerError::IdentityNotQueuedForDeletion)
+        }
+    }
+
     /// Queues a deletion from the merkle tree.
     ///
     /// # Errors
@@ -430,6 +624,55 @@ impl App {
         Ok(())
     }
 
+    /// Pauses a background task, e.g. to stop batches from being submitted
+    /// during a contract upgrade. The API keeps serving requests while tasks
+    /// are paused.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the paused state can't be persisted.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn pause_task(&self, task: TaskKind) -> Result<(), ServerError> {
+        self.identity_committer.pause_task(task).await?;
+        Ok(())
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if the paused state can't be persisted.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn resume_task(&self, task: TaskKind) -> Result<(), ServerError> {
+        self.identity_committer.resume_task(task).await?;
+        Ok(())
+    }
+
+    /// Reports whether the background tasks are alive, i.e. none of them has
+    /// stopped or stalled. Tasks that are restarting after a failure are still
+    /// considered alive.
+    pub async fn liveness(&self) -> HealthResponse {
+        self.task_health(TaskHealthReport::is_alive).await
+    }
+
+    /// Reports whether the background tasks are ready, i.e. all of them are
+    /// running and making progress.
+    pub async fn readiness(&self) -> HealthResponse {
+        self.task_health(TaskHealthReport::is_healthy).await
+    }
+
+    async fn task_health(&self, is_healthy: fn(&TaskHealthReport) -> bool) -> HealthResponse {
+        let Some(tasks) = self.identity_committer.task_health().await else {
+            return HealthResponse {
+                healthy: false,
+                tasks:   vec![],
+            };
+        };
+
+        HealthResponse {
+            healthy: tasks.iter().all(is_healthy),
+            tasks,
+        }
+    }
+
     fn merge_env_provers(options: prover::Options, existing_provers: &mut Provers) -> Provers {
         let options_set: HashSet<ProverConfiguration> = options
             .prover_urls
@@ -440,6 +683,8 @@ impl App {
                 batch_size:  opt.batch_size,
                 timeout_s:   opt.timeout_s,
                 prover_type: opt.prover_type,
+                protocol:    opt.protocol,
+                auth:        opt.auth,
             })
             .collect();
 
@@ -456,9 +701,12 @@ impl App {
         commitment.lt(&self.snark_scalar_field)
     }
 
+    /// Adds a prover for the given batch size, or another replica if there
+    /// already is one.
+    ///
     /// # Errors
     ///
-    /// Will return `Err` if the provided batch size already exists.
+    /// Will return `Err` if the prover already exists for the batch size.
     /// Will return `Err` if the batch size fails to write to database.
     #[instrument(level = "debug", skip(self))]
     pub async fn add_batch_size(
@@ -467,13 +715,29 @@ impl App {
         batch_size: usize,
         timeout_seconds: u64,
         prover_type: ProverType,
+        protocol: ProverProtocol,
+        auth: Option<ProverAuth>,
     ) -> Result<(), ServerError> {
         self.identity_manager
-            .add_batch_size(&url, batch_size, timeout_seconds, prover_type)
+            .add_batch_size(
+                &url,
+                batch_size,
+                timeout_seconds,
+                prover_type,
+                protocol,
+                auth.clone(),
+            )
             .await?;
 
         self.database
-            .insert_prover_configuration(batch_size, url, timeout_seconds, prover_type)
+            .insert_prover_configuration(
+                batch_size,
+                url,
+                timeout_seconds,
+                prover_type,
+                protocol,
+                auth.as_ref(),
+            )
             .await?;
 
         Ok(())
@@ -488,12 +752,16 @@ impl App {
         &self,
         batch_size: usize,
         prover_type: ProverType,
+        url: Option<&str>,
     ) -> Result<(), ServerError> {
-        self.identity_manager
-            .remove_batch_size(batch_size, prover_type)
+        let url = self
+            .identity_manager
+            .remove_batch_size(batch_size, prover_type, url)
             .await?;
 
-        self.database.remove_prover(batch_size, prover_type).await?;
+        self.database
+            .remove_prover(batch_size, prover_type, url.as_deref())
+            .await?;
 
         Ok(())
     }
@@ -6,23 +6,37 @@ use std::collections::HashMap;
 use std::sync::Arc;
 
 use anyhow::{anyhow, Context};
+use chrono::Utc;
 use clap::Parser;
 use ethers::providers::Middleware;
+use ethers::types::transaction::eip2718::TypedTransaction;
 use ethers::types::{Address, H256, U256};
+use ethers::utils::keccak256;
 use semaphore::Field;
-use tokio::sync::RwLockReadGuard;
+use tokio::sync::{RwLock, RwLockReadGuard};
 use tracing::{error, info, instrument, warn};
 
 use self::abi::{BridgedWorldId, DeleteIdentitiesCall, WorldId};
+use crate::database::types::SubmittedTransactionStatus;
+use crate::database::Database;
 use crate::ethereum::write::TransactionId;
-use crate::ethereum::{Ethereum, ReadProvider};
+use crate::ethereum::{Ethereum, Operation, ReadProvider};
 use crate::prover::identity::Identity;
-use crate::prover::map::{DeletionProverMap, InsertionProverMap, ReadOnlyInsertionProver};
-use crate::prover::{Proof, Prover, ProverConfiguration, ProverType, ReadOnlyProver};
+use crate::prover::map::{
+    DeletionProverMap, InsertionProverMap, ProverMap, ReadOnlyInsertionProver,
+};
+use crate::prover::{
+    compute_deletion_proof_input_hash, compute_insertion_proof_input_hash, CapabilityCheck, Proof,
+    ProofVerifiers, Prover, ProverAuth, ProverConfiguration, ProverPool, ProverProtocol,
+    ProverType, ReadOnlyProver,
+};
 use crate::serde_utils::JsonStrWrapper;
 use crate::server::error::Error as ServerError;
 use crate::utils::index_packing::unpack_indices;
 
+/// How long generated proofs are kept in the proof cache, in hours.
+const PROOF_CACHE_RETENTION_HOURS: i64 = 24;
+
 /// Configuration options for the component responsible for interacting with the
 /// contract.
 #[derive(Clone, Debug, PartialEq, Eq, Parser)]
@@ -64,6 +78,8 @@ pub struct IdentityManager {
     secondary_abis:       Vec<BridgedWorldId<ReadProvider>>,
     initial_leaf_value:   Field,
     tree_depth:           usize,
+    /// Verifiers for the proofs of provers added at runtime.
+    verifiers:            ProofVerifiers,
 }
 
 impl IdentityManager {
@@ -82,6 +98,7 @@ impl IdentityManager {
         ethereum: Ethereum,
         insertion_prover_map: InsertionProverMap,
         deletion_prover_map: DeletionProverMap,
+        verifiers: ProofVerifiers,
     ) -> anyhow::Result<Self>
     where
         Self: Sized,
@@ -136,8 +153,13 @@ impl IdentityManager {
             secondary_abis,
             initial_leaf_value,
             tree_depth,
+            verifiers,
         };
 
+        // Reject provers loaded from the configuration or the database that don't
+        // match their circuit parameters before they're used.
+        identity_manager.probe_provers().await;
+
         Ok(identity_manager)
     }
 
@@ -175,28 +197,70 @@ impl IdentityManager {
         Ok(())
     }
 
+    /// Refreshes the circuit parameters and the health of the provers that
+    /// are due for it. Prover selection only reads the results, so that it
+    /// never waits on a slow prover.
+    pub async fn probe_provers(&self) {
+        for prover_map in [&self.insertion_prover_map, &self.deletion_prover_map] {
+            let prover_map = prover_map.read().await;
+
+            futures::future::join_all(prover_map.values().map(|pool| async move {
+                pool.refresh_capabilities(self.tree_depth).await;
+                pool.check_health().await;
+            }))
+            .await;
+        }
+    }
+
     pub async fn get_suitable_insertion_prover(
         &self,
         num_identities: usize,
-    ) -> anyhow::Result<ReadOnlyProver<Prover>> {
-        let prover_map = self.insertion_prover_map.read().await;
-
-        match RwLockReadGuard::try_map(prover_map, |map| map.get(num_identities)) {
-            Ok(p) => anyhow::Ok(p),
-            Err(_) => Err(anyhow!(
-                "No available prover for batch size: {num_identities}"
-            )),
-        }
+    ) -> anyhow::Result<ReadOnlyProver<ProverPool>> {
+        Self::get_suitable_prover(&self.insertion_prover_map, num_identities).await
     }
 
     pub async fn get_suitable_deletion_prover(
         &self,
         num_identities: usize,
-    ) -> anyhow::Result<ReadOnlyProver<Prover>> {
-        let prover_map = self.deletion_prover_map.read().await;
+    ) -> anyhow::Result<ReadOnlyProver<ProverPool>> {
+        Self::get_suitable_prover(&self.deletion_prover_map, num_identities).await
+    }
 
-        match RwLockReadGuard::try_map(prover_map, |map| map.get(num_identities)) {
-            Ok(p) => anyhow::Ok(p),
+    /// Selects the smallest healthy prover that can handle `num_identities`.
+    ///
+    /// If the smallest prover is unhealthy a larger one is used instead, in
+    /// which case the batch is padded up to the larger size. If none of the
+    /// provers are healthy the smallest one is returned anyway, so that the
+    /// batch is still attempted. Provers that don't match their configuration
+    /// are never returned.
+    async fn get_suitable_prover(
+        prover_map: &RwLock<ProverMap<ProverPool>>,
+        num_identities: usize,
+    ) -> anyhow::Result<ReadOnlyProver<ProverPool>> {
+        let prover_map = prover_map.read().await;
+
+        let optimal_batch_size = prover_map
+            .get_matching(num_identities, ProverPool::is_compatible)
+            .map(ProverPool::batch_size);
+        let prover = RwLockReadGuard::try_map(prover_map, |map| {
+            map.get_matching(num_identities, ProverPool::is_healthy)
+                .or_else(|| map.get_matching(num_identities, ProverPool::is_compatible))
+        });
+
+        match prover {
+            Ok(prover) => {
+                if optimal_batch_size != Some(prover.batch_size()) {
+                    warn!(
+                        num_identities,
+                        ?optimal_batch_size,
+                        batch_size = prover.batch_size(),
+                        prover_type = ?prover.prover_type(),
+                        "Optimal prover is unhealthy, falling back to a larger prover."
+                    );
+                }
+
+                anyhow::Ok(prover)
+            }
             Err(_) => Err(anyhow!(
                 "No available prover for batch size: {num_identities}"
             )),
@@ -207,8 +271,11 @@ impl IdentityManager {
         Ok(self.abi.get_root_history_expiry().call().await?)
     }
 
-    #[instrument(level = "debug", skip(prover, identity_commitments))]
+    /// Generates an insertion proof, reusing a proof for the same inputs from
+    /// the proof cache if one was already generated.
+    #[instrument(level = "debug", skip(database, prover, identity_commitments))]
     pub async fn prepare_insertion_proof(
+        database: &Database,
         prover: ReadOnlyInsertionProver<'_>,
         start_index: usize,
         pre_root: U256,
@@ -219,6 +286,21 @@ impl IdentityManager {
 
         let actual_start_index: u32 = start_index.try_into()?;
 
+        let commitments: Vec<U256> = identity_commitments
+            .iter()
+            .map(|id| id.commitment)
+            .collect();
+        let input_hash = compute_insertion_proof_input_hash(
+            actual_start_index,
+            pre_root,
+            post_root,
+            &commitments,
+        );
+
+        if let Some(proof) = Self::get_cached_proof(database, input_hash).await {
+            return Ok(proof);
+        }
+
         info!(
             "Sending {} identities to prover of batch size {}",
             batch_size,
@@ -234,17 +316,29 @@ impl IdentityManager {
             )
             .await?;
 
+        Self::cache_proof(database, input_hash, &proof_data).await;
+
         Ok(proof_data)
     }
 
-    #[instrument(level = "debug", skip(prover, identity_commitments))]
+    /// Generates a deletion proof, reusing a proof for the same inputs from
+    /// the proof cache if one was already generated.
+    #[instrument(level = "debug", skip(database, prover, identity_commitments))]
     pub async fn prepare_deletion_proof(
-        prover: ReadOnlyProver<'_, Prover>,
+        database: &Database,
+        prover: ReadOnlyProver<'_, ProverPool>,
         pre_root: U256,
         deletion_indices: Vec<u32>,
         identity_commitments: Vec<Identity>,
         post_root: U256,
     ) -> anyhow::Result<Proof> {
+        let input_hash =
+            compute_deletion_proof_input_hash(deletion_indices.clone(), pre_root, post_root);
+
+        if let Some(proof) = Self::get_cached_proof(database, input_hash).await {
+            return Ok(proof);
+        }
+
         info!(
             "Sending {} identities to prover of batch size {}",
             identity_commitments.len(),
@@ -255,12 +349,57 @@ impl IdentityManager {
             .generate_deletion_proof(pre_root, post_root, deletion_indices, identity_commitments)
             .await?;
 
+        Self::cache_proof(database, input_hash, &proof_data).await;
+
         Ok(proof_data)
     }
 
-    #[instrument(level = "debug", skip(self, identity_commitments, proof_data))]
+    /// Looks up a proof in the proof cache. Failing to read the cache is not
+    /// fatal, as the proof can always be generated again.
+    async fn get_cached_proof(database: &Database, input_hash: U256) -> Option<Proof> {
+        match database.get_cached_proof(input_hash).await {
+            Ok(Some(proof)) => {
+                info!(?input_hash, "Reusing cached proof.");
+                Some(proof)
+            }
+            Ok(None) => None,
+            Err(error) => {
+                warn!(?input_hash, ?error, "Failed to read the proof cache.");
+                None
+            }
+        }
+    }
+
+    async fn cache_proof(database: &Database, input_hash: U256, proof: &Proof) {
+        if let Err(error) = database.insert_cached_proof(input_hash, proof).await {
+            warn!(?input_hash, ?error, "Failed to cache proof.");
+        }
+    }
+
+    /// Removes the cached proofs that are too old to still be needed. This
+    /// runs once at startup, so that caching a proof stays a single write.
+    pub async fn prune_proof_cache(database: &Database) {
+        let expired = Utc::now() - chrono::Duration::hours(PROOF_CACHE_RETENTION_HOURS);
+
+        match database.prune_cached_proofs(expired).await {
+            Ok(pruned_count) => info!(pruned_count, "Pruned the proof cache."),
+            Err(error) => warn!(?error, "Failed to prune the proof cache."),
+        }
+    }
+
+    /// Whether batches of the `operation` should wait for the base fee to
+    /// drop before they are sent.
+    pub async fn should_defer(&self, operation: Operation) -> anyhow::Result<bool> {
+        self.ethereum.should_defer(operation).await
+    }
+
+    #[instrument(
+        level = "debug",
+        skip(self, database, identity_commitments, proof_data)
+    )]
     pub async fn register_identities(
         &self,
+        database: &Database,
         start_index: usize,
         pre_root: U256,
         post_root: U256,
@@ -289,16 +428,20 @@ impl IdentityManager {
             )
             .tx;
 
-        self.ethereum
-            .send_transaction(register_identities_transaction, true)
-            .await
-            .map_err(|tx_err| anyhow!("{}", tx_err.to_string()))
+        self.send_batch(
+            database,
+            register_identities_transaction,
+            pre_root,
+            post_root,
+        )
+        .await
     }
 
     // TODO: docs
-    #[instrument(level = "debug")]
+    #[instrument(level = "debug", skip(self, database))]
     pub async fn delete_identities(
         &self,
+        database: &Database,
         deletion_proof: Proof,
         packed_deletion_indices: Vec<u8>,
         pre_root: U256,
@@ -316,10 +459,62 @@ impl IdentityManager {
             )
             .tx;
 
-        self.ethereum
-            .send_transaction(register_identities_transaction, true)
-            .await
-            .map_err(|tx_err| anyhow!("{}", tx_err.to_string()))
+        self.send_batch(
+            database,
+            register_identities_transaction,
+            pre_root,
+            post_root,
+        )
+        .await
+    }
+
+    /// Sends a batch and records it in the database, so that it can be
+    /// monitored again after a restart.
+    async fn send_batch(
+        &self,
+        database: &Database,
+        transaction: TypedTransaction,
+        pre_root: U256,
+        post_root: U256,
+    ) -> anyhow::Result<TransactionId> {
+        // Batches that would revert are rejected before they are sent. A batch built
+        // on top of batches that are still in flight would always revert with
+        // `NotLatestRoot`, since its pre root isn't on chain yet, so it can't be
+        // simulated.
+        if database
+            .get_pending_submitted_transactions()
+            .await?
+            .is_empty()
+        {
+            self.ethereum.simulate_transaction(&transaction).await?;
+        }
+
+        let calldata_hash = H256::from(keccak256(
+            transaction.data().map(AsRef::as_ref).unwrap_or_default(),
+        ));
+
+        // The batch is recorded before it's sent, so that it's monitored after a
+        // restart even if the sequencer stops right after sending it
+        let submitted_id = database
+            .insert_submitted_transaction(calldata_hash, &pre_root.into(), &post_root.into())
+            .await?;
+
+        let transaction_id = match self.ethereum.send_transaction(transaction, true).await {
+            Ok(transaction_id) => transaction_id,
+            Err(tx_err) => {
+                database
+                    .set_submitted_batch_status(submitted_id, SubmittedTransactionStatus::Failed)
+                    .await?;
+
+                return Err(anyhow!("{}", tx_err.to_string()));
+            }
+        };
+
+        database
+            .set_submitted_transaction_id(submitted_id, transaction_id.as_ref())
+            .await?;
+
+        Ok(transaction_id)
     }
 
     #[instrument(level = "debug", skip(self))]
@@ -336,11 +531,25 @@ impl IdentityManager {
         Ok(pending_identities)
     }
 
-    /// Waits until all the pending transactions have been mined or failed
+    /// Waits until all the pending transactions have been mined or failed,
+    /// including the ones recorded in the database that the write provider no
+    /// longer reports as pending.
     #[instrument(level = "debug", skip_all)]
-    pub async fn await_clean_slate(&self) -> anyhow::Result<()> {
+    pub async fn await_clean_slate(&self, database: &Database) -> anyhow::Result<()> {
         // Await for all pending transactions
-        let pending_identities = self.fetch_pending_identities().await?;
+        let mut pending_identities = self.fetch_pending_identities().await?;
+
+        // Batches that were never handed to the write provider are checked by their
+        // post root once the transactions are monitored again
+        for submitted in database.get_pending_submitted_transactions().await? {
+            let Some(transaction_id) = submitted.transaction_id else {
+                continue;
+            };
+
+            if !pending_identities.iter().any(|tx| tx.0 == transaction_id) {
+                pending_identities.push(TransactionId(transaction_id));
+            }
+        }
 
         for pending_identity_tx in pending_identities {
             // Ignores the result of each transaction - we only care about a clean slate in
@@ -353,7 +562,45 @@ impl IdentityManager {
 
     #[instrument(level = "debug", skip_all)]
     pub async fn latest_root(&self) -> anyhow::Result<U256> {
-        let latest_root = self.abi.latest_root().call().await?;
+        let latest_root = self
+            .ethereum
+            .provider()
+            .quorum_call(self.abi.latest_root())
+            .await?;
+
+        Ok(latest_root)
+    }
+
+    /// The latest root as of the given block.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn latest_root_at(&self, block: u64) -> anyhow::Result<U256> {
+        let latest_root = self
+            .ethereum
+            .provider()
+            .quorum_call(self.abi.latest_root().block(block))
+            .await?;
+
+        Ok(latest_root)
+    }
+
+    /// The latest root of the bridged contract at `address` as of the given
+    /// block of its chain.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn secondary_latest_root_at(
+        &self,
+        address: Address,
+        block: u64,
+    ) -> anyhow::Result<U256> {
+        let bridged_world_id = self
+            .secondary_abis
+            .iter()
+            .find(|bridged_world_id| bridged_world_id.address() == address)
+            .ok_or_else(|| anyhow!("Unknown secondary contract {address:?}"))?;
+
+        let latest_root = bridged_world_id
+            .client()
+            .quorum_call(bridged_world_id.latest_root().block(block))
+            .await?;
 
         Ok(latest_root)
     }
@@ -391,7 +638,11 @@ impl IdentityManager {
 
     #[instrument(level = "debug", skip_all)]
     pub async fn is_root_mined(&self, root: U256) -> anyhow::Result<bool> {
-        let (root_on_mainnet, ..) = self.abi.query_root(root).call().await?;
+        let (root_on_mainnet, ..) = self
+            .ethereum
+            .provider()
+            .quorum_call(self.abi.query_root(root))
+            .await?;
 
         if root_on_mainnet.is_zero() {
             return Ok(false);
@@ -402,7 +653,11 @@ impl IdentityManager {
 
     #[instrument(level = "debug", skip_all)]
     pub async fn is_root_mined_multi_chain(&self, root: U256) -> anyhow::Result<bool> {
-        let (root_on_mainnet, ..) = self.abi.query_root(root).call().await?;
+        let (root_on_mainnet, ..) = self
+            .ethereum
+            .provider()
+            .quorum_call(self.abi.query_root(root))
+            .await?;
 
         if root_on_mainnet.is_zero() {
             return Ok(false);
@@ -413,7 +668,10 @@ impl IdentityManager {
 
             // root_history only returns superseded roots, so we must also check the latest
             // root
-            let latest_root = bridged_world_id.latest_root().call().await?;
+            let latest_root = bridged_world_id
+                .client()
+                .quorum_call(bridged_world_id.latest_root())
+                .await?;
 
             // If root is not superseded and it's not the latest root
             // then it's not mined
@@ -425,37 +683,64 @@ impl IdentityManager {
         Ok(true)
     }
 
+    /// Adds a prover for the given batch size. If there already is a prover
+    /// for the batch size, the new one is added as a replica.
+    ///
+    /// The prover is queried for its circuit parameters first, and rejected if
+    /// they don't match the provided configuration.
+    ///
     /// # Errors
     ///
-    /// Will return `Err` if the provided batch size already exists.
+    /// Will return `Err` if a prover with the same URL already exists for the
+    /// batch size, or if the prover doesn't match the configuration.
     pub async fn add_batch_size(
         &self,
         url: &impl ToString,
         batch_size: usize,
         timeout_seconds: u64,
         prover_type: ProverType,
+        protocol: ProverProtocol,
+        auth: Option<ProverAuth>,
     ) -> Result<(), ServerError> {
-        let mut map = match prover_type {
-            ProverType::Insertion => self.insertion_prover_map.write().await,
-            ProverType::Deletion => self.deletion_prover_map.write().await,
-        };
-
-        if map.batch_size_exists(batch_size) {
-            return Err(ServerError::BatchSizeAlreadyExists);
-        }
-
         let prover = Prover::new(&ProverConfiguration {
             url: url.to_string(),
             batch_size,
             prover_type,
             timeout_s: timeout_seconds,
+            protocol,
+            auth,
         })?;
 
-        map.add(batch_size, prover);
+        if let CapabilityCheck::Incompatible(reason) =
+            prover.check_capabilities(self.tree_depth).await
+        {
+            return Err(ServerError::ProverCapabilityMismatch(reason));
+        }
+
+        let mut map = match prover_type {
+            ProverType::Insertion => self.insertion_prover_map.write().await,
+            ProverType::Deletion => self.deletion_prover_map.write().await,
+        };
+
+        match map.get_exact_mut(batch_size) {
+            Some(pool) if pool.contains_url(&prover.url()) => {
+                return Err(ServerError::BatchSizeAlreadyExists);
+            }
+            Some(pool) => pool.add_replica(prover)?,
+            None => {
+                let mut pool = ProverPool::new(prover);
+                pool.set_verifier(self.verifiers.get(prover_type, batch_size));
+                map.add(batch_size, pool);
+            }
+        }
 
         Ok(())
     }
 
+    /// Removes the provers for the given batch size, or only the replica that
+    /// `url` refers to if one is provided. Returns the URL of that replica,
+    /// which may differ from `url` if it was referred to by its redacted URL.
+    ///
     /// # Errors
     ///
     /// Will return `Err` if the batch size requested for removal doesn't exist
@@ -464,21 +749,45 @@ impl IdentityManager {
         &self,
         batch_size: usize,
         prover_type: ProverType,
-    ) -> Result<(), ServerError> {
+        url: Option<&str>,
+    ) -> Result<Option<String>, ServerError> {
         let mut map = match prover_type {
             ProverType::Insertion => self.insertion_prover_map.write().await,
             ProverType::Deletion => self.deletion_prover_map.write().await,
         };
 
+        let Some(pool) = map.get_exact_mut(batch_size) else {
+            return Err(ServerError::NoSuchBatchSize);
+        };
+
+        let url = match url {
+            Some(url) => match pool.resolve_url(url) {
+                Ok(Some(url)) => Some(url),
+                Ok(None) => return Err(ServerError::NoSuchBatchSize),
+                Err(error) => {
+                    warn!(?error, "Failed to resolve the prover URL.");
+                    return Err(ServerError::AmbiguousProverUrl);
+                }
+            },
+            None => None,
+        };
+
+        // Removing one of several replicas leaves the batch size in place
+        if let Some(url) = &url {
+            if pool.replica_count() > 1 {
+                pool.remove_replica(url);
+                return Ok(Some(url.clone()));
+            }
+        }
+
         if map.len() == 1 {
             warn!("Attempting to remove the last batch size.");
             return Err(ServerError::CannotRemoveLastBatchSize);
         }
 
-        match map.remove(batch_size) {
-            Some(_) => Ok(()),
-            None => Err(ServerError::NoSuchBatchSize),
-        }
+        map.remove(batch_size);
+
+        Ok(url)
     }
 
     pub async fn list_batch_sizes(&self) -> Result<Vec<ProverConfiguration>, ServerError> {
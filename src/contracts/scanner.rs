@@ -1,10 +1,25 @@
+use std::collections::VecDeque;
+
+use anyhow::anyhow;
 use ethers::providers::Middleware;
-use ethers::types::{Address, BlockNumber, Filter, FilterBlockOption, Log, Topic, ValueOrArray};
+use ethers::types::{
+    Address, BlockNumber, Filter, FilterBlockOption, Log, Topic, ValueOrArray, H256,
+};
+use tracing::warn;
+
+/// The number of scanned windows whose last block hash is remembered. Reorgs
+/// deeper than the remembered windows can't be recovered from.
+const TRACKED_WINDOWS: usize = 128;
 
 pub struct BlockScanner<T> {
     read_provider: T,
     current_block: u64,
     window_size:   u64,
+    /// Blocks younger than this many blocks are not scanned yet.
+    confirmations: u64,
+    /// The number and hash of the last block of each scanned window, oldest
+    /// first.
+    scanned:       VecDeque<(u64, H256)>,
 }
 
 impl<T> BlockScanner<T>
@@ -12,13 +27,20 @@ where
     T: Middleware,
     <T as Middleware>::Error: 'static,
 {
-    pub async fn new_latest(read_provider: T, window_size: u64) -> anyhow::Result<Self> {
+    pub async fn new_latest(
+        read_provider: T,
+        window_size: u64,
+        confirmations: u64,
+    ) -> anyhow::Result<Self> {
         let latest_block = read_provider.get_block_number().await?;
+        let current_block = latest_block.as_u64().saturating_sub(confirmations);
 
         Ok(Self {
             read_provider,
-            current_block: latest_block.as_u64(),
+            current_block,
             window_size,
+            confirmations,
+            scanned: VecDeque::new(),
         })
     }
 
@@ -27,7 +49,12 @@ where
         address: Option<ValueOrArray<Address>>,
         topics: [Option<Topic>; 4],
     ) -> anyhow::Result<Vec<Log>> {
-        let latest_block = self.read_provider.get_block_number().await?.as_u64();
+        let latest_block = self
+            .read_provider
+            .get_block_number()
+            .await?
+            .as_u64()
+            .saturating_sub(self.confirmations);
 
         if self.current_block >= latest_block {
             return Ok(Vec::new());
@@ -36,25 +63,215 @@ where
         let from_block = self.current_block;
         let to_block = latest_block.min(from_block + self.window_size);
 
-        let next_current_block = to_block + 1;
-
-        let from_block = Some(BlockNumber::Number(from_block.into()));
-        let to_block = Some(BlockNumber::Number(to_block.into()));
+        // The hash is fetched before the logs, so that a reorg in between is
+        // detected by the next call to `detect_reorg`
+        let to_block_hash = self
+            .block_hash(to_block)
+            .await?
+            .ok_or_else(|| anyhow!("Block {to_block} not found"))?;
 
         let logs = self
             .read_provider
             .get_logs(&Filter {
                 block_option: FilterBlockOption::Range {
-                    from_block,
-                    to_block,
+                    from_block: Some(BlockNumber::Number(from_block.into())),
+                    to_block:   Some(BlockNumber::Number(to_block.into())),
                 },
                 address,
                 topics,
             })
             .await?;
 
-        self.current_block = next_current_block;
+        self.current_block = to_block + 1;
+
+        self.scanned.push_back((to_block, to_block_hash));
+        if self.scanned.len() > TRACKED_WINDOWS {
+            self.scanned.pop_front();
+        }
 
         Ok(logs)
     }
+
+    /// Checks whether the last scanned block is still part of the chain. If it
+    /// isn't, scanning is moved back to after the most recent scanned block
+    /// that still is, and its number is returned. Logs from the blocks after it
+    /// must be considered removed.
+    pub async fn detect_reorg(&mut self) -> anyhow::Result<Option<u64>> {
+        let Some(&(last_block, last_hash)) = self.scanned.back() else {
+            return Ok(None);
+        };
+
+        // Blocks that are missing were removed by a reorg to a shorter chain
+        if self.block_hash(last_block).await? == Some(last_hash) {
+            return Ok(None);
+        }
+
+        while let Some((block, hash)) = self.scanned.pop_back() {
+            if self.block_hash(block).await? == Some(hash) {
+                warn!(
+                    fork_block = block,
+                    last_block, "Chain reorg detected, scanning again"
+                );
+
+                self.scanned.push_back((block, hash));
+                self.current_block = block + 1;
+
+                return Ok(Some(block));
+            }
+        }
+
+        Err(anyhow!(
+            "Chain reorg deeper than the last {TRACKED_WINDOWS} scanned windows at block \
+             {last_block}"
+        ))
+    }
+
+    async fn block_hash(&self, block: u64) -> anyhow::Result<Option<H256>> {
+        Ok(self
+            .read_provider
+            .get_block(block)
+            .await?
+            .and_then(|block| block.hash))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Debug;
+    use std::sync::{Arc, Mutex};
+
+    use async_trait::async_trait;
+    use ethers::providers::{JsonRpcClient, Provider, ProviderError};
+    use ethers::types::{Block, U64};
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+    use serde_json::json;
+
+    use super::*;
+
+    #[derive(Debug, Default)]
+    struct Chain {
+        hashes:       Vec<H256>,
+        logs:         Vec<Log>,
+        log_requests: Vec<(U64, U64)>,
+    }
+
+    impl Chain {
+        fn extend_to(&mut self, len: u64) {
+            let start = self.hashes.len() as u64;
+            self.hashes.extend((start..len).map(H256::from_low_u64_be));
+        }
+
+        fn reorg_from(&mut self, block: usize) {
+            for hash in &mut self.hashes[block..] {
+                *hash = H256::from_low_u64_be(hash.to_low_u64_be() + 1000);
+            }
+        }
+    }
+
+    /// Serves blocks and logs from a chain that can be changed by the test.
+    #[derive(Clone, Debug, Default)]
+    struct MockChain(Arc<Mutex<Chain>>);
+
+    #[async_trait]
+    impl JsonRpcClient for MockChain {
+        type Error = ProviderError;
+
+        async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+        where
+            T: Debug + Serialize + Send + Sync,
+            R: DeserializeOwned,
+        {
+            let params = serde_json::to_value(params)?;
+            let mut chain = self.0.lock().unwrap();
+
+            let response = match method {
+                "eth_blockNumber" => json!(U64::from(chain.hashes.len() as u64 - 1)),
+                "eth_getBlockByNumber" => {
+                    let number: U64 = serde_json::from_value(params[0].clone())?;
+                    let block = Block::<H256> {
+                        hash: chain.hashes.get(number.as_usize()).copied(),
+                        number: Some(number),
+                        ..Block::default()
+                    };
+
+                    serde_json::to_value(block)?
+                }
+                "eth_getLogs" => {
+                    let from_block: U64 = serde_json::from_value(params[0]["fromBlock"].clone())?;
+                    let to_block: U64 = serde_json::from_value(params[0]["toBlock"].clone())?;
+                    chain.log_requests.push((from_block, to_block));
+
+                    let logs: Vec<_> = chain
+                        .logs
+                        .iter()
+                        .filter(|log| {
+                            log.block_number
+                                .is_some_and(|block| (from_block..=to_block).contains(&block))
+                        })
+                        .collect();
+
+                    serde_json::to_value(logs)?
+                }
+                _ => {
+                    return Err(ProviderError::CustomError(format!(
+                        "Unexpected method {method}"
+                    )))
+                }
+            };
+
+            Ok(serde_json::from_value(response)?)
+        }
+    }
+
+    #[tokio::test]
+    async fn reorged_blocks_are_scanned_again() -> anyhow::Result<()> {
+        let mock_chain = MockChain::default();
+        {
+            let mut chain = mock_chain.0.lock().unwrap();
+            chain.extend_to(10);
+            chain.logs.push(Log {
+                block_number: Some(16.into()),
+                ..Log::default()
+            });
+        }
+
+        let mut scanner = BlockScanner::new_latest(Provider::new(mock_chain.clone()), 4, 0).await?;
+        assert_eq!(scanner.current_block, 9);
+
+        mock_chain.0.lock().unwrap().extend_to(20);
+
+        assert!(scanner.next(None, Default::default()).await?.is_empty());
+        let logs = scanner.next(None, Default::default()).await?;
+        assert_eq!(logs.len(), 1);
+        assert_eq!(scanner.current_block, 19);
+
+        // Nothing changed yet
+        assert_eq!(scanner.detect_reorg().await?, None);
+
+        mock_chain.0.lock().unwrap().reorg_from(15);
+
+        assert_eq!(scanner.detect_reorg().await?, Some(13));
+        assert_eq!(scanner.current_block, 14);
+
+        let logs = scanner.next(None, Default::default()).await?;
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].block_number, Some(16.into()));
+        assert_eq!(scanner.current_block, 19);
+
+        let log_requests = mock_chain.0.lock().unwrap().log_requests.clone();
+        assert_eq!(log_requests, vec![
+            (9.into(), 13.into()),
+            (14.into(), 18.into()),
+            (14.into(), 18.into()),
+        ]);
+
+        // Blocks removed by a reorg to a shorter chain count as changed
+        mock_chain.0.lock().unwrap().hashes.truncate(16);
+
+        assert_eq!(scanner.detect_reorg().await?, Some(13));
+        assert_eq!(scanner.current_block, 14);
+
+        Ok(())
+    }
 }
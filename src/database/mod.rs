@@ -9,18 +9,27 @@ use std::collections::HashSet;
 use anyhow::{anyhow, Context, Error as ErrReport};
 use chrono::{DateTime, Utc};
 use clap::Parser;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{Address, H256, U256};
 use sqlx::migrate::{Migrate, MigrateDatabase, Migrator};
 use sqlx::pool::PoolOptions;
+use sqlx::postgres::PgRow;
 use sqlx::{Executor, Pool, Postgres, Row};
 use thiserror::Error;
 use tracing::{error, info, instrument, warn};
 
-use self::types::{DeletionEntry, LatestDeletionEntry, RecoveryEntry};
+use self::types::{
+    DeletionEntry, LatestDeletionEntry, LocalTransaction, LocalTransactionStatus, RecoveryEntry,
+    SubmittedTransaction, SubmittedTransactionStatus,
+};
 use crate::identity_tree::{Hash, RootItem, Status, TreeItem, TreeUpdate};
 
 pub mod types;
-use crate::prover::{ProverConfiguration, ProverType, Provers};
+use crate::prover::{
+    normalize_url, Proof, ProverAuth, ProverConfiguration, ProverProtocol, ProverType, Provers,
+};
 use crate::secret::SecretUrl;
+use crate::task_monitor::health::TaskKind;
 
 // Statically link in migration files
 static MIGRATOR: Migrator = sqlx::migrate!("schemas/database");
@@ -260,6 +269,84 @@ impl Database {
         Ok(())
     }
 
+    /// Marks the identities and roots after a given root hash as pending again,
+    /// e.g. after a chain reorg removed them from the chain. Without a root,
+    /// all of them are marked as pending.
+    #[instrument(skip(self), level = "debug")]
+    pub async fn rollback_roots_after(&self, root: Option<&Hash>) -> Result<(), Error> {
+        let pending_status = Status::Pending;
+
+        let mut tx = self.pool.begin().await?;
+
+        let root_id = match root {
+            Some(root) => {
+                let Some(root_id) = Self::get_id_by_root(&mut tx, root).await? else {
+                    return Err(Error::MissingRoot { root: *root });
+                };
+
+                root_id as i64
+            }
+            None => 0,
+        };
+
+        let update_next_roots = sqlx::query(
+            r#"
+            UPDATE identities
+            SET    status = $2, mined_at = NULL
+            WHERE  id > $1
+            AND    status <> $2
+            "#,
+        )
+        .bind(root_id)
+        .bind(<&str>::from(pending_status));
+
+        tx.execute(update_next_roots).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Marks the mined identities and roots after a given root hash as
+    /// processed again, e.g. after a chain reorg removed them from a secondary
+    /// chain. Without a root, all of them are marked as processed.
+    #[instrument(skip(self), level = "debug")]
+    pub async fn rollback_mined_roots_after(&self, root: Option<&Hash>) -> Result<(), Error> {
+        let mined_status = Status::Mined;
+        let processed_status = Status::Processed;
+
+        let mut tx = self.pool.begin().await?;
+
+        let root_id = match root {
+            Some(root) => {
+                let Some(root_id) = Self::get_id_by_root(&mut tx, root).await? else {
+                    return Err(Error::MissingRoot { root: *root });
+                };
+
+                root_id as i64
+            }
+            None => 0,
+        };
+
+        let update_next_roots = sqlx::query(
+            r#"
+            UPDATE identities
+            SET    status = $2
+            WHERE  id > $1
+            AND    status = $3
+            "#,
+        )
+        .bind(root_id)
+        .bind(<&str>::from(processed_status))
+        .bind(<&str>::from(mined_status));
+
+        tx.execute(update_next_roots).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
     pub async fn get_next_leaf_index(&self) -> Result<usize, Error> {
         let query = sqlx::query(
             r#"
@@ -403,31 +490,441 @@ impl Database {
         Ok(result.get::<i64, _>(0) as i32)
     }
 
+    pub async fn pause_task(&self, task: TaskKind) -> Result<(), Error> {
+        let query = sqlx::query(
+            r#"
+                INSERT INTO paused_tasks (task)
+                VALUES ($1)
+                ON CONFLICT (task) DO NOTHING
+            "#,
+        )
+        .bind(task.name());
+
+        self.pool.execute(query).await?;
+        Ok(())
+    }
+
+    pub async fn resume_task(&self, task: TaskKind) -> Result<(), Error> {
+        let query = sqlx::query(
+            r#"
+                DELETE FROM paused_tasks
+                WHERE task = $1
+            "#,
+        )
+        .bind(task.name());
+
+        self.pool.execute(query).await?;
+        Ok(())
+    }
+
+    /// Stores a generated proof, keyed by the input hash of the batch it
+    /// proves.
+    pub async fn insert_cached_proof(&self, input_hash: U256, proof: &Proof) -> Result<(), Error> {
+        let words: [U256; 8] = proof.clone().into();
+        let proof_bytes: Vec<u8> = words.iter().flat_map(u256_to_bytes).collect();
+
+        let query = sqlx::query(
+            r#"
+                INSERT INTO proof_cache (input_hash, proof)
+                VALUES ($1, $2)
+                ON CONFLICT (input_hash) DO UPDATE SET proof = EXCLUDED.proof
+            "#,
+        )
+        .bind(u256_to_bytes(&input_hash).to_vec())
+        .bind(proof_bytes);
+
+        self.pool.execute(query).await?;
+        Ok(())
+    }
+
+    pub async fn get_cached_proof(&self, input_hash: U256) -> Result<Option<Proof>, Error> {
+        let query = sqlx::query(
+            r#"
+                SELECT proof
+                FROM proof_cache
+                WHERE input_hash = $1
+            "#,
+        )
+        .bind(u256_to_bytes(&input_hash).to_vec());
+
+        let Some(row) = self.pool.fetch_optional(query).await? else {
+            return Ok(None);
+        };
+
+        let proof_bytes = row.get::<Vec<u8>, _>(0);
+        if proof_bytes.len() != 8 * 32 {
+            warn!(?input_hash, "Ignoring malformed cached proof.");
+            return Ok(None);
+        }
+
+        let mut words = [U256::zero(); 8];
+        for (word, bytes) in words.iter_mut().zip(proof_bytes.chunks_exact(32)) {
+            *word = U256::from_big_endian(bytes);
+        }
+
+        Ok(Some(words.into()))
+    }
+
+    /// Removes cached proofs that were stored before `older_than`.
+    pub async fn prune_cached_proofs(&self, older_than: DateTime<Utc>) -> Result<u64, Error> {
+        let query = sqlx::query(
+            r#"
+                DELETE FROM proof_cache
+                WHERE created_at < $1
+            "#,
+        )
+        .bind(older_than);
+
+        let result = self.pool.execute(query).await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Records a transaction sent by the local signer.
+    pub async fn insert_local_transaction(
+        &self,
+        id: &str,
+        signer: Address,
+        nonce: u64,
+        data_hash: H256,
+        tx: &TypedTransaction,
+        tx_hash: H256,
+    ) -> Result<(), Error> {
+        let query = sqlx::query(
+            r#"
+                INSERT INTO local_transactions (id, signer, nonce, data_hash, tx, tx_hashes)
+                VALUES ($1, $2, $3, $4, $5, ARRAY[$6])
+            "#,
+        )
+        .bind(id)
+        .bind(signer.as_bytes())
+        .bind(nonce as i64)
+        .bind(data_hash.as_bytes())
+        .bind(serde_json::to_string(tx)?)
+        .bind(tx_hash.as_bytes());
+
+        self.pool.execute(query).await?;
+        Ok(())
+    }
+
+    /// Records that a new version of a local transaction, for example with
+    /// higher fees, was sent.
+    pub async fn update_local_transaction(
+        &self,
+        id: &str,
+        tx: &TypedTransaction,
+        tx_hash: H256,
+    ) -> Result<(), Error> {
+        let query = sqlx::query(
+            r#"
+                UPDATE local_transactions
+                SET tx = $2, tx_hashes = array_append(tx_hashes, $3), sent_at = CURRENT_TIMESTAMP
+                WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(serde_json::to_string(tx)?)
+        .bind(tx_hash.as_bytes());
+
+        self.pool.execute(query).await?;
+        Ok(())
+    }
+
+    /// Removes a local transaction that could not be sent, which frees up its
+    /// nonce.
+    pub async fn delete_local_transaction(&self, id: &str) -> Result<(), Error> {
+        let query = sqlx::query(
+            r#"
+                DELETE FROM local_transactions
+                WHERE id = $1
+            "#,
+        )
+        .bind(id);
+
+        self.pool.execute(query).await?;
+        Ok(())
+    }
+
+    pub async fn set_local_transaction_status(
+        &self,
+        id: &str,
+        status: LocalTransactionStatus,
+    ) -> Result<(), Error> {
+        let query = sqlx::query(
+            r#"
+                UPDATE local_transactions
+                SET status = $2
+                WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(status);
+
+        self.pool.execute(query).await?;
+        Ok(())
+    }
+
+    pub async fn get_local_transaction(&self, id: &str) -> Result<Option<LocalTransaction>, Error> {
+        let query = sqlx::query(
+            r#"
+                SELECT id, nonce, tx, tx_hashes, status, sent_at
+                FROM local_transactions
+                WHERE id = $1
+            "#,
+        )
+        .bind(id);
+
+        self.pool
+            .fetch_optional(query)
+            .await?
+            .map(|row| Self::local_transaction_from_row(&row))
+            .transpose()
+    }
+
+    /// Returns the pending transactions of the `signer`, ordered by nonce.
+    pub async fn get_pending_local_transactions(
+        &self,
+        signer: Address,
+    ) -> Result<Vec<LocalTransaction>, Error> {
+        let query = sqlx::query(
+            r#"
+                SELECT id, nonce, tx, tx_hashes, status, sent_at
+                FROM local_transactions
+                WHERE signer = $1 AND status = 'Pending'
+                ORDER BY nonce ASC
+            "#,
+        )
+        .bind(signer.as_bytes());
+
+        self.pool
+            .fetch_all(query)
+            .await?
+            .iter()
+            .map(Self::local_transaction_from_row)
+            .collect()
+    }
+
+    /// Returns the pending transaction of the `signer` with the given calldata
+    /// hash, if there is one.
+    pub async fn get_pending_local_transaction_by_data(
+        &self,
+        signer: Address,
+        data_hash: H256,
+    ) -> Result<Option<LocalTransaction>, Error> {
+        let query = sqlx::query(
+            r#"
+                SELECT id, nonce, tx, tx_hashes, status, sent_at
+                FROM local_transactions
+                WHERE signer = $1 AND data_hash = $2 AND status = 'Pending'
+                ORDER BY nonce DESC
+                LIMIT 1
+            "#,
+        )
+        .bind(signer.as_bytes())
+        .bind(data_hash.as_bytes());
+
+        self.pool
+            .fetch_optional(query)
+            .await?
+            .map(|row| Self::local_transaction_from_row(&row))
+            .transpose()
+    }
+
+    /// Returns the nonce following the last transaction sent by the `signer`.
+    pub async fn get_next_local_nonce(&self, signer: Address) -> Result<Option<u64>, Error> {
+        let query = sqlx::query(
+            r#"
+                SELECT MAX(nonce) + 1
+                FROM local_transactions
+                WHERE signer = $1
+            "#,
+        )
+        .bind(signer.as_bytes());
+
+        let row = self.pool.fetch_one(query).await?;
+
+        Ok(row.get::<Option<i64>, _>(0).map(|nonce| nonce as u64))
+    }
+
+    /// Records a batch that is about to be sent on chain, and returns the id
+    /// of the record.
+    pub async fn insert_submitted_transaction(
+        &self,
+        calldata_hash: H256,
+        pre_root: &Hash,
+        post_root: &Hash,
+    ) -> Result<i64, Error> {
+        let query = sqlx::query(
+            r#"
+                INSERT INTO submitted_transactions (calldata_hash, pre_root, post_root)
+                VALUES ($1, $2, $3)
+                RETURNING id
+            "#,
+        )
+        .bind(calldata_hash.as_bytes())
+        .bind(pre_root)
+        .bind(post_root);
+
+        let row = self.pool.fetch_one(query).await?;
+        Ok(row.get::<i64, _>(0))
+    }
+
+    /// Sets the id the write provider assigned to a recorded batch once it's
+    /// sent.
+    pub async fn set_submitted_transaction_id(
+        &self,
+        id: i64,
+        transaction_id: &str,
+    ) -> Result<(), Error> {
+        let query = sqlx::query(
+            r#"
+                UPDATE submitted_transactions
+                SET transaction_id = $2
+                WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(transaction_id);
+
+        self.pool.execute(query).await?;
+        Ok(())
+    }
+
+    /// Sets the status of a recorded batch by the id of the record, for
+    /// batches that may not have a transaction id.
+    pub async fn set_submitted_batch_status(
+        &self,
+        id: i64,
+        status: SubmittedTransactionStatus,
+    ) -> Result<(), Error> {
+        let query = sqlx::query(
+            r#"
+                UPDATE submitted_transactions
+                SET status = $2
+                WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(status);
+
+        self.pool.execute(query).await?;
+        Ok(())
+    }
+
+    pub async fn set_submitted_transaction_status(
+        &self,
+        transaction_id: &str,
+        status: SubmittedTransactionStatus,
+    ) -> Result<(), Error> {
+        let query = sqlx::query(
+            r#"
+                UPDATE submitted_transactions
+                SET status = $2
+                WHERE transaction_id = $1
+            "#,
+        )
+        .bind(transaction_id)
+        .bind(status);
+
+        self.pool.execute(query).await?;
+        Ok(())
+    }
+
+    /// Returns the batches that were sent but not yet mined, in the order they
+    /// were sent.
+    pub async fn get_pending_submitted_transactions(
+        &self,
+    ) -> Result<Vec<SubmittedTransaction>, Error> {
+        let query = sqlx::query(
+            r#"
+                SELECT id, transaction_id, calldata_hash, pre_root, post_root, status, created_at
+                FROM submitted_transactions
+                WHERE status = 'Pending'
+                ORDER BY id ASC
+            "#,
+        );
+
+        Ok(self
+            .pool
+            .fetch_all(query)
+            .await?
+            .iter()
+            .map(|row| SubmittedTransaction {
+                id:             row.get::<i64, _>(0),
+                transaction_id: row.get::<Option<String>, _>(1),
+                calldata_hash:  H256::from_slice(&row.get::<Vec<u8>, _>(2)),
+                pre_root:       row.get::<Hash, _>(3),
+                post_root:      row.get::<Hash, _>(4),
+                status:         row.get::<SubmittedTransactionStatus, _>(5),
+                created_at:     row.get::<DateTime<Utc>, _>(6),
+            })
+            .collect())
+    }
+
+    fn local_transaction_from_row(row: &PgRow) -> Result<LocalTransaction, Error> {
+        let tx_hashes = row
+            .get::<Vec<Vec<u8>>, _>(3)
+            .iter()
+            .map(|hash| H256::from_slice(hash))
+            .collect();
+
+        Ok(LocalTransaction {
+            id: row.get::<String, _>(0),
+            nonce: row.get::<i64, _>(1) as u64,
+            tx: serde_json::from_str(&row.get::<String, _>(2))?,
+            tx_hashes,
+            status: row.get::<LocalTransactionStatus, _>(4),
+            sent_at: row.get::<DateTime<Utc>, _>(5),
+        })
+    }
+
+    pub async fn get_paused_tasks(&self) -> Result<Vec<TaskKind>, Error> {
+        let query = sqlx::query(
+            r#"
+                SELECT task
+                FROM paused_tasks
+            "#,
+        );
+
+        let result = self.pool.fetch_all(query).await?;
+
+        Ok(result
+            .iter()
+            .filter_map(|row| TaskKind::from_name(&row.get::<String, _>(0)))
+            .collect())
+    }
+
     pub async fn get_provers(&self) -> Result<Provers, Error> {
         let query = sqlx::query(
             r#"
-                SELECT batch_size, url, timeout_s, prover_type
+                SELECT batch_size, url, timeout_s, prover_type, protocol, auth
                 FROM provers
             "#,
         );
 
         let result = self.pool.fetch_all(query).await?;
 
-        Ok(result
+        result
             .iter()
             .map(|row| {
                 let batch_size = row.get::<i64, _>(0) as usize;
                 let url = row.get::<String, _>(1);
                 let timeout_s = row.get::<i64, _>(2) as u64;
                 let prover_type = row.get::<ProverType, _>(3);
-                ProverConfiguration {
+                let protocol = row.get::<ProverProtocol, _>(4);
+                let auth = row
+                    .get::<Option<String>, _>(5)
+                    .map(|auth| serde_json::from_str::<ProverAuth>(&auth))
+                    .transpose()?;
+                Ok(ProverConfiguration {
                     url,
                     timeout_s,
                     batch_size,
                     prover_type,
-                }
+                    protocol,
+                    auth,
+                })
             })
-            .collect::<Provers>())
+            .collect()
     }
 
     pub async fn insert_prover_configuration(
@@ -436,19 +933,23 @@ impl Database {
         url: impl ToString,
         timeout_seconds: u64,
         prover_type: ProverType,
+        protocol: ProverProtocol,
+        auth: Option<&ProverAuth>,
     ) -> Result<(), Error> {
-        let url = url.to_string();
+        let url = normalize_url(&url.to_string());
 
         let query = sqlx::query(
             r#"
-                INSERT INTO provers (batch_size, url, timeout_s, prover_type)
-                VALUES ($1, $2, $3, $4)
+                INSERT INTO provers (batch_size, url, timeout_s, prover_type, protocol, auth)
+                VALUES ($1, $2, $3, $4, $5, $6)
             "#,
         )
         .bind(batch_size as i64)
         .bind(url)
         .bind(timeout_seconds as i64)
-        .bind(prover_type);
+        .bind(prover_type)
+        .bind(protocol)
+        .bind(auth.map(ProverAuth::to_exposed_json));
 
         self.pool.execute(query).await?;
 
@@ -462,35 +963,73 @@ impl Database {
 
         let mut query_builder = sqlx::QueryBuilder::new(
             r#"
-                  INSERT INTO provers (batch_size, url, timeout_s, prover_type)
+                  INSERT INTO provers (batch_size, url, timeout_s, prover_type, protocol, auth)
             "#,
         );
 
         query_builder.push_values(provers, |mut b, prover| {
             b.push_bind(prover.batch_size as i64)
-                .push_bind(prover.url)
+                .push_bind(normalize_url(&prover.url))
                 .push_bind(prover.timeout_s as i64)
-                .push_bind(prover.prover_type);
+                .push_bind(prover.prover_type)
+                .push_bind(prover.protocol)
+                .push_bind(prover.auth.as_ref().map(ProverAuth::to_exposed_json));
         });
 
+        query_builder.push(" ON CONFLICT DO NOTHING");
+
         let query = query_builder.build();
 
         self.pool.execute(query).await?;
         Ok(())
     }
 
+    /// Removes the provers for the given batch size and prover type. If a
+    /// `url` is provided only the prover with that URL is removed.
     pub async fn remove_prover(
         &self,
         batch_size: usize,
         prover_type: ProverType,
+        url: Option<&str>,
     ) -> Result<(), Error> {
+        let urls = match url {
+            Some(url) => {
+                let url = normalize_url(url);
+
+                // Provers inserted before their URLs were normalized are stored as
+                // they were provided
+                let query = sqlx::query(
+                    r#"
+                      SELECT url FROM provers
+                      WHERE batch_size = $1 AND prover_type = $2
+                    "#,
+                )
+                .bind(batch_size as i64)
+                .bind(prover_type);
+
+                let urls: Vec<String> = self
+                    .pool
+                    .fetch_all(query)
+                    .await?
+                    .into_iter()
+                    .map(|row| row.get::<String, _>(0))
+                    .filter(|stored_url| normalize_url(stored_url) == url)
+                    .collect();
+
+                Some(urls)
+            }
+            None => None,
+        };
+
         let query = sqlx::query(
             r#"
-              DELETE FROM provers WHERE batch_size = $1 AND prover_type = $2
+              DELETE FROM provers
+              WHERE batch_size = $1 AND prover_type = $2 AND ($3::TEXT[] IS NULL OR url = ANY($3))
             "#,
         )
         .bind(batch_size as i64)
-        .bind(prover_type);
+        .bind(prover_type)
+        .bind(urls);
 
         self.pool.execute(query).await?;
 
@@ -533,6 +1072,36 @@ impl Database {
         Ok(())
     }
 
+    /// Removes the recovery of `existing_commitment`, if there is one.
+    /// Removes a queued deletion together with the recovery it belongs to, if
+    /// any.
+    pub async fn cancel_deletion(&self, commitment: &Hash) -> Result<(), Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let remove_deletion = sqlx::query(
+            r#"
+            DELETE FROM deletions
+            WHERE commitment = $1
+            "#,
+        )
+        .bind(commitment);
+
+        let remove_recovery = sqlx::query(
+            r#"
+            DELETE FROM recoveries
+            WHERE existing_commitment = $1
+            "#,
+        )
+        .bind(commitment);
+
+        tx.execute(remove_deletion).await?;
+        tx.execute(remove_recovery).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
     pub async fn get_latest_deletion(&self) -> Result<LatestDeletionEntry, Error> {
         let query =
             sqlx::query("SELECT deletion_timestamp FROM latest_deletion_root WHERE Lock = 'X';");
@@ -666,9 +1235,28 @@ impl Database {
             query = query.bind(commitment);
         }
 
-        query.execute(&self.pool).await?;
+        query.execute(&self.pool).await?;
+
+        Ok(())
+    }
+
+    /// Returns the earliest time at which an unprocessed identity with the
+    /// given status becomes eligible for insertion.
+    pub async fn get_next_eligibility_timestamp(
+        &self,
+        status: Status,
+    ) -> Result<Option<DateTime<Utc>>, Error> {
+        let query = sqlx::query(
+            r#"
+                SELECT MIN(eligibility) FROM unprocessed_identities
+                WHERE status = $1
+            "#,
+        )
+        .bind(<&str>::from(status));
+
+        let row = self.pool.fetch_one(query).await?;
 
-        Ok(())
+        Ok(row.get::<Option<DateTime<Utc>>, _>(0))
     }
 
     pub async fn get_eligible_unprocessed_commitments(
@@ -722,6 +1310,58 @@ impl Database {
         Ok(None)
     }
 
+    pub async fn get_unprocessed_commitment(
+        &self,
+        commitment: &Hash,
+    ) -> Result<Option<types::UnprocessedCommitment>, Error> {
+        let query = sqlx::query(
+            r#"
+                SELECT commitment, status, created_at, processed_at, error_message, eligibility
+                FROM unprocessed_identities
+                WHERE commitment = $1
+            "#,
+        )
+        .bind(commitment);
+
+        let Some(row) = self.pool.fetch_optional(query).await? else {
+            return Ok(None);
+        };
+
+        let status = row
+            .get::<&str, _>(1)
+            .parse()
+            .expect("Status is unreadable, database is corrupt");
+
+        Ok(Some(types::UnprocessedCommitment {
+            commitment: row.get::<Hash, _>(0),
+            status,
+            created_at: row.get::<_, _>(2),
+            processed_at: row.get::<_, _>(3),
+            error_message: row.get::<_, _>(4),
+            eligibility_timestamp: row.get::<_, _>(5),
+        }))
+    }
+
+    /// Removes an unprocessed identity, but only if it has not yet become
+    /// eligible for insertion. Returns `true` if an entry was removed.
+    pub async fn remove_scheduled_unprocessed_identity(
+        &self,
+        commitment: &Hash,
+    ) -> Result<bool, Error> {
+        let query = sqlx::query(
+            r#"
+                DELETE FROM unprocessed_identities
+                WHERE commitment = $1 AND status = $2 AND eligibility > CURRENT_TIMESTAMP
+            "#,
+        )
+        .bind(commitment)
+        .bind(<&str>::from(Status::New));
+
+        let result = self.pool.execute(query).await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
     pub async fn remove_unprocessed_identity(&self, commitment: &Hash) -> Result<(), Error> {
         let query = sqlx::query(
             r#"
@@ -791,6 +1431,15 @@ pub enum Error {
 
     #[error("Tried to mine missing root {root:?}")]
     MissingRoot { root: Hash },
+
+    #[error("failed to serialize or deserialize a stored value: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+fn u256_to_bytes(value: &U256) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+    bytes
 }
 
 #[cfg(test)]
@@ -801,15 +1450,18 @@ mod test {
 
     use anyhow::Context;
     use chrono::{Days, Utc};
-    use ethers::types::U256;
+    use ethers::types::transaction::eip2718::TypedTransaction;
+    use ethers::types::{Address, Eip1559TransactionRequest, H256, U256};
     use postgres_docker_utils::DockerContainerGuard;
     use ruint::Uint;
     use semaphore::Field;
 
+    use super::types::{LocalTransactionStatus, SubmittedTransactionStatus};
     use super::{Database, Options};
     use crate::identity_tree::{Hash, Status};
-    use crate::prover::{ProverConfiguration, ProverType};
-    use crate::secret::SecretUrl;
+    use crate::prover::{Proof, ProverAuth, ProverConfiguration, ProverProtocol, ProverType};
+    use crate::secret::{SecretString, SecretUrl};
+    use crate::task_monitor::health::TaskKind;
 
     macro_rules! assert_same_time {
         ($a:expr, $b:expr, $diff:expr) => {
@@ -943,16 +1595,22 @@ mod test {
 
         provers.insert(ProverConfiguration {
             batch_size:  100,
-            url:         "http://localhost:8080".to_string(),
+            url:         "http://localhost:8080/".to_string(),
             timeout_s:   100,
             prover_type: ProverType::Insertion,
+            protocol:    ProverProtocol::Sync,
+            auth:        None,
         });
 
         provers.insert(ProverConfiguration {
             batch_size:  100,
-            url:         "http://localhost:8080".to_string(),
+            url:         "http://localhost:8080/".to_string(),
             timeout_s:   100,
             prover_type: ProverType::Deletion,
+            protocol:    ProverProtocol::Async,
+            auth:        Some(ProverAuth::Bearer {
+                token: SecretString::new("token".into()),
+            }),
         });
 
         provers
@@ -964,16 +1622,23 @@ mod test {
 
         let mock_prover_configuration_0 = ProverConfiguration {
             batch_size:  100,
-            url:         "http://localhost:8080".to_string(),
+            url:         "http://localhost:8080/".to_string(),
             timeout_s:   100,
             prover_type: ProverType::Insertion,
+            protocol:    ProverProtocol::Sync,
+            auth:        None,
         };
 
         let mock_prover_configuration_1 = ProverConfiguration {
             batch_size:  100,
-            url:         "http://localhost:8081".to_string(),
+            url:         "http://localhost:8081/".to_string(),
             timeout_s:   100,
             prover_type: ProverType::Deletion,
+            protocol:    ProverProtocol::Async,
+            auth:        Some(ProverAuth::Hmac {
+                key:    SecretString::new("shared-key".into()),
+                header: "x-signature".into(),
+            }),
         };
 
         db.insert_prover_configuration(
@@ -981,6 +1646,8 @@ mod test {
             mock_prover_configuration_0.url.clone(),
             mock_prover_configuration_0.timeout_s,
             mock_prover_configuration_0.prover_type,
+            mock_prover_configuration_0.protocol,
+            mock_prover_configuration_0.auth.as_ref(),
         )
         .await?;
 
@@ -989,6 +1656,8 @@ mod test {
             mock_prover_configuration_1.url.clone(),
             mock_prover_configuration_1.timeout_s,
             mock_prover_configuration_1.prover_type,
+            mock_prover_configuration_1.protocol,
+            mock_prover_configuration_1.auth.as_ref(),
         )
         .await?;
 
@@ -997,6 +1666,16 @@ mod test {
         assert!(provers.contains(&mock_prover_configuration_0));
         assert!(provers.contains(&mock_prover_configuration_1));
 
+        let prover = provers.get(&mock_prover_configuration_1);
+        assert_eq!(
+            prover.map(|prover| prover.protocol),
+            Some(ProverProtocol::Async)
+        );
+        assert_eq!(
+            prover.and_then(|prover| prover.auth.as_ref()),
+            mock_prover_configuration_1.auth.as_ref()
+        );
+
         Ok(())
     }
 
@@ -1019,10 +1698,100 @@ mod test {
         let mock_provers = mock_provers();
 
         db.insert_provers(mock_provers.clone()).await?;
-        db.remove_prover(100, ProverType::Insertion).await?;
+        db.remove_prover(100, ProverType::Insertion, None).await?;
+        let provers = db.get_provers().await?;
+
+        // Only the deletion prover of the same batch size is left
+        assert_eq!(provers.len(), 1);
+        assert!(provers
+            .iter()
+            .all(|prover| prover.prover_type == ProverType::Deletion));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_remove_prover_by_url() -> anyhow::Result<()> {
+        let (db, _db_container) = setup_db().await?;
+
+        let replica_0 = ProverConfiguration {
+            batch_size:  100,
+            url:         "http://localhost:8080".to_string(),
+            timeout_s:   100,
+            prover_type: ProverType::Insertion,
+            protocol:    ProverProtocol::Sync,
+            auth:        None,
+        };
+        let replica_1 = ProverConfiguration {
+            url: "http://localhost:8081".to_string(),
+            ..replica_0.clone()
+        };
+
+        db.insert_provers([replica_0.clone(), replica_1.clone()].into())
+            .await?;
+        // Inserting the same prover again is a no-op
+        db.insert_provers([replica_0.clone()].into()).await?;
+        assert_eq!(db.get_provers().await?.len(), 2);
+
+        // URLs are compared after normalization
+        db.remove_prover(100, ProverType::Insertion, Some("http://localhost:8081/"))
+            .await?;
         let provers = db.get_provers().await?;
 
-        assert_eq!(provers, HashSet::new());
+        assert_eq!(
+            provers,
+            HashSet::from([ProverConfiguration {
+                url: "http://localhost:8080/".to_string(),
+                ..replica_0
+            }])
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_remove_prover_stored_without_normalization() -> anyhow::Result<()> {
+        let (db, _db_container) = setup_db().await?;
+
+        let query = sqlx::query(
+            r#"
+                INSERT INTO provers (batch_size, url, timeout_s, prover_type, protocol)
+                VALUES (100, 'http://LOCALHOST:8080', 100, $1, $2)
+            "#,
+        )
+        .bind(ProverType::Insertion)
+        .bind(ProverProtocol::Sync);
+        db.pool.execute(query).await?;
+
+        db.remove_prover(100, ProverType::Insertion, Some("http://localhost:8080"))
+            .await?;
+
+        assert!(db.get_provers().await?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pause_and_resume_tasks() -> anyhow::Result<()> {
+        let (db, _db_container) = setup_db().await?;
+
+        assert!(db.get_paused_tasks().await?.is_empty());
+
+        db.pause_task(TaskKind::ProcessIdentities).await?;
+        db.pause_task(TaskKind::DeleteIdentities).await?;
+        // Pausing a task twice is a no-op
+        db.pause_task(TaskKind::ProcessIdentities).await?;
+
+        let paused_tasks: HashSet<TaskKind> = db.get_paused_tasks().await?.into_iter().collect();
+        assert_eq!(
+            paused_tasks,
+            HashSet::from([TaskKind::ProcessIdentities, TaskKind::DeleteIdentities])
+        );
+
+        db.resume_task(TaskKind::ProcessIdentities).await?;
+
+        let paused_tasks = db.get_paused_tasks().await?;
+        assert_eq!(paused_tasks, vec![TaskKind::DeleteIdentities]);
 
         Ok(())
     }
@@ -1046,6 +1815,32 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_cancel_deletion() -> anyhow::Result<()> {
+        let (db, _db_container) = setup_db().await?;
+
+        let old_commitments = mock_identities(2);
+        let new_commitments = mock_identities(4)[2..].to_vec();
+
+        for (leaf_index, (old, new)) in old_commitments.iter().zip(&new_commitments).enumerate() {
+            db.insert_new_deletion(leaf_index, old).await?;
+            db.insert_new_recovery(old, new).await?;
+        }
+
+        db.cancel_deletion(&old_commitments[0]).await?;
+
+        let deletions = db.get_deletions().await?;
+        assert_eq!(deletions.len(), 1);
+        assert_eq!(deletions[0].commitment, old_commitments[1]);
+
+        let recoveries = db.get_recoveries().await?;
+        assert_eq!(recoveries.len(), 1);
+        assert_eq!(recoveries[0].existing_commitment, old_commitments[1]);
+        assert_eq!(recoveries[0].new_commitment, new_commitments[1]);
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_insert_new_deletion() -> anyhow::Result<()> {
         let (db, _db_container) = setup_db().await?;
@@ -1122,6 +1917,88 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_get_next_eligibility_timestamp() -> anyhow::Result<()> {
+        let (db, _db_container) = setup_db().await?;
+
+        assert!(db
+            .get_next_eligibility_timestamp(Status::New)
+            .await?
+            .is_none());
+
+        let eligibility_timestamp_0 = Utc::now()
+            .checked_add_days(Days::new(7))
+            .expect("Could not create eligibility timestamp");
+        let eligibility_timestamp_1 = Utc::now()
+            .checked_add_days(Days::new(1))
+            .expect("Could not create eligibility timestamp");
+
+        db.insert_new_identity(Uint::from(1), eligibility_timestamp_0)
+            .await?;
+        db.insert_new_identity(Uint::from(2), eligibility_timestamp_1)
+            .await?;
+
+        let next_eligibility = db
+            .get_next_eligibility_timestamp(Status::New)
+            .await?
+            .context("Missing next eligibility timestamp")?;
+        assert_eq!(
+            next_eligibility.timestamp(),
+            eligibility_timestamp_1.timestamp()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_remove_scheduled_unprocessed_identity() -> anyhow::Result<()> {
+        let (db, _db_container) = setup_db().await?;
+
+        // Insert an identity that is eligible immediately
+        let commitment_0: Uint<256, 4> = Uint::from(1);
+        db.insert_new_identity(commitment_0, Utc::now()).await?;
+
+        // Insert an identity that is scheduled for the future
+        let commitment_1: Uint<256, 4> = Uint::from(2);
+        let eligibility_timestamp_1 = Utc::now()
+            .checked_add_days(Days::new(7))
+            .expect("Could not create eligibility timestamp");
+        db.insert_new_identity(commitment_1, eligibility_timestamp_1)
+            .await?;
+
+        let scheduled = db
+            .get_unprocessed_commitment(&commitment_1)
+            .await?
+            .context("Missing scheduled commitment")?;
+        assert_eq!(scheduled.status, Status::New);
+        assert_eq!(
+            scheduled.eligibility_timestamp.timestamp(),
+            eligibility_timestamp_1.timestamp()
+        );
+
+        // Eligible identities cannot be removed
+        assert!(
+            !db.remove_scheduled_unprocessed_identity(&commitment_0)
+                .await?
+        );
+        assert!(db
+            .get_unprocessed_commitment(&commitment_0)
+            .await?
+            .is_some());
+
+        // Scheduled identities can be removed
+        assert!(
+            db.remove_scheduled_unprocessed_identity(&commitment_1)
+                .await?
+        );
+        assert!(db
+            .get_unprocessed_commitment(&commitment_1)
+            .await?
+            .is_none());
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_identity_is_queued_for_deletion() -> anyhow::Result<()> {
         let (db, _db_container) = setup_db().await?;
@@ -1368,6 +2245,73 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn rollback_roots_after_marks_next_roots_as_pending() -> anyhow::Result<()> {
+        let (db, _db_container) = setup_db().await?;
+
+        let num_identities = 6;
+
+        let identities = mock_identities(num_identities);
+        let roots = mock_roots(num_identities);
+
+        for i in 0..num_identities {
+            db.insert_pending_identity(i, &identities[i], &roots[i])
+                .await
+                .context("Inserting identity")?;
+        }
+
+        db.mark_root_as_processed(&roots[4]).await?;
+        db.mark_root_as_mined(&roots[2]).await?;
+
+        db.rollback_roots_after(Some(&roots[1])).await?;
+
+        assert_roots_are(&db, &roots[..2], Status::Mined).await?;
+        assert_roots_are(&db, &roots[2..], Status::Pending).await?;
+
+        db.rollback_roots_after(None).await?;
+
+        assert_roots_are(&db, &roots, Status::Pending).await?;
+
+        let unknown_root = db.rollback_roots_after(Some(&Hash::from(42))).await;
+        assert!(unknown_root.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn rollback_mined_roots_after_marks_next_roots_as_processed() -> anyhow::Result<()> {
+        let (db, _db_container) = setup_db().await?;
+
+        let num_identities = 6;
+
+        let identities = mock_identities(num_identities);
+        let roots = mock_roots(num_identities);
+
+        for i in 0..num_identities {
+            db.insert_pending_identity(i, &identities[i], &roots[i])
+                .await
+                .context("Inserting identity")?;
+        }
+
+        db.mark_root_as_processed(&roots[4]).await?;
+        db.mark_root_as_mined(&roots[2]).await?;
+
+        db.rollback_mined_roots_after(Some(&roots[1])).await?;
+
+        assert_roots_are(&db, &roots[..2], Status::Mined).await?;
+        assert_roots_are(&db, &roots[2..5], Status::Processed).await?;
+        assert_roots_are(&db, &roots[5..], Status::Pending).await?;
+
+        db.rollback_mined_roots_after(None).await?;
+
+        assert_roots_are(&db, &roots[..5], Status::Processed).await?;
+
+        let unknown_root = db.rollback_mined_roots_after(Some(&Hash::from(42))).await;
+        assert!(unknown_root.is_err());
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn mark_root_as_processed_marks_next_roots() -> anyhow::Result<()> {
         let (db, _db_container) = setup_db().await?;
@@ -1736,4 +2680,131 @@ mod test {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_proof_cache() -> anyhow::Result<()> {
+        let (db, _db_container) = setup_db().await?;
+
+        let input_hash = U256::from(42);
+        let proof = Proof::from([
+            U256::from(1),
+            U256::from(2),
+            U256::from(3),
+            U256::from(4),
+            U256::from(5),
+            U256::from(6),
+            U256::from(7),
+            U256::MAX,
+        ]);
+
+        assert!(db.get_cached_proof(input_hash).await?.is_none());
+
+        db.insert_cached_proof(input_hash, &proof).await?;
+        assert_eq!(db.get_cached_proof(input_hash).await?, Some(proof.clone()));
+        assert!(db.get_cached_proof(U256::from(43)).await?.is_none());
+
+        let pruned = db
+            .prune_cached_proofs(Utc::now() - chrono::Duration::hours(1))
+            .await?;
+        assert_eq!(pruned, 0);
+
+        let pruned = db
+            .prune_cached_proofs(Utc::now() + chrono::Duration::hours(1))
+            .await?;
+        assert_eq!(pruned, 1);
+        assert!(db.get_cached_proof(input_hash).await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_local_transactions() -> anyhow::Result<()> {
+        let (db, _db_container) = setup_db().await?;
+
+        let signer = Address::repeat_byte(1);
+        let data_hash = H256::repeat_byte(2);
+        let tx: TypedTransaction = Eip1559TransactionRequest::new()
+            .nonce(7)
+            .data(vec![1, 2, 3])
+            .into();
+
+        assert_eq!(db.get_next_local_nonce(signer).await?, None);
+
+        db.insert_local_transaction("tx-7", signer, 7, data_hash, &tx, H256::repeat_byte(3))
+            .await?;
+        assert_eq!(db.get_next_local_nonce(signer).await?, Some(8));
+        assert_eq!(db.get_next_local_nonce(Address::zero()).await?, None);
+
+        let mut bumped = tx.clone();
+        bumped.set_gas_price(U256::from(100));
+        db.update_local_transaction("tx-7", &bumped, H256::repeat_byte(4))
+            .await?;
+
+        let pending = db.get_pending_local_transactions(signer).await?;
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].tx, bumped);
+        assert_eq!(pending[0].tx_hashes, vec![
+            H256::repeat_byte(3),
+            H256::repeat_byte(4)
+        ]);
+
+        let by_data = db
+            .get_pending_local_transaction_by_data(signer, data_hash)
+            .await?;
+        assert_eq!(by_data.map(|tx| tx.id), Some("tx-7".to_string()));
+
+        db.set_local_transaction_status("tx-7", LocalTransactionStatus::Mined)
+            .await?;
+        assert!(db.get_pending_local_transactions(signer).await?.is_empty());
+        assert_eq!(
+            db.get_local_transaction("tx-7").await?.map(|tx| tx.status),
+            Some(LocalTransactionStatus::Mined)
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_submitted_transactions() -> anyhow::Result<()> {
+        let (db, _db_container) = setup_db().await?;
+
+        let roots = mock_roots(4);
+
+        let id_1 = db
+            .insert_submitted_transaction(H256::repeat_byte(1), &roots[0], &roots[1])
+            .await?;
+        let id_2 = db
+            .insert_submitted_transaction(H256::repeat_byte(2), &roots[1], &roots[2])
+            .await?;
+        // A batch that was never handed to the write provider
+        let id_3 = db
+            .insert_submitted_transaction(H256::repeat_byte(3), &roots[2], &roots[3])
+            .await?;
+
+        db.set_submitted_transaction_id(id_1, "tx-1").await?;
+        db.set_submitted_transaction_id(id_2, "tx-2").await?;
+
+        let pending = db.get_pending_submitted_transactions().await?;
+        assert_eq!(pending.len(), 3);
+        assert_eq!(pending[0].id, id_1);
+        assert_eq!(pending[0].transaction_id.as_deref(), Some("tx-1"));
+        assert_eq!(pending[0].calldata_hash, H256::repeat_byte(1));
+        assert_eq!(pending[0].pre_root, roots[0]);
+        assert_eq!(pending[0].post_root, roots[1]);
+        assert_eq!(pending[0].status, SubmittedTransactionStatus::Pending);
+        assert_eq!(pending[1].transaction_id.as_deref(), Some("tx-2"));
+        assert_eq!(pending[2].id, id_3);
+        assert_eq!(pending[2].transaction_id, None);
+
+        db.set_submitted_transaction_status("tx-1", SubmittedTransactionStatus::Mined)
+            .await?;
+        db.set_submitted_batch_status(id_3, SubmittedTransactionStatus::Failed)
+            .await?;
+
+        let pending = db.get_pending_submitted_transactions().await?;
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].transaction_id.as_deref(), Some("tx-2"));
+
+        Ok(())
+    }
 }
@@ -1,4 +1,6 @@
 use chrono::{DateTime, Utc};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::H256;
 
 use crate::identity_tree::{Hash, Status};
 
@@ -25,3 +27,48 @@ pub struct DeletionEntry {
     pub leaf_index: usize,
     pub commitment: Hash,
 }
+
+#[derive(Debug, Copy, Clone, sqlx::Type, PartialEq, Eq)]
+#[sqlx(type_name = "local_transaction_status_enum", rename_all = "PascalCase")]
+pub enum LocalTransactionStatus {
+    Pending,
+    Mined,
+    Failed,
+}
+
+/// A transaction sent by the local signer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalTransaction {
+    pub id:        String,
+    pub nonce:     u64,
+    /// The latest version of the unsigned transaction.
+    pub tx:        TypedTransaction,
+    /// The hashes of every version of the transaction that was sent.
+    pub tx_hashes: Vec<H256>,
+    pub status:    LocalTransactionStatus,
+    pub sent_at:   DateTime<Utc>,
+}
+
+#[derive(Debug, Copy, Clone, sqlx::Type, PartialEq, Eq)]
+#[sqlx(
+    type_name = "submitted_transaction_status_enum",
+    rename_all = "PascalCase"
+)]
+pub enum SubmittedTransactionStatus {
+    Pending,
+    Mined,
+    Failed,
+}
+
+/// A batch sent on chain through any write provider.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubmittedTransaction {
+    pub id:             i64,
+    /// `None` if the batch wasn't handed to the write provider yet.
+    pub transaction_id: Option<String>,
+    pub calldata_hash:  H256,
+    pub pre_root:       Hash,
+    pub post_root:      Hash,
+    pub status:         SubmittedTransactionStatus,
+    pub created_at:     DateTime<Utc>,
+}
@@ -0,0 +1,173 @@
+//! The fee policy applied to the transactions sent by the sequencer.
+
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::U256;
+
+/// The number of wei in a gwei.
+const GWEI: u64 = 1_000_000_000;
+
+/// How urgently the batches of an operation have to be submitted on chain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Urgency {
+    /// Batches are sent regardless of the base fee. They still pay at most
+    /// the maximum fee per gas, so they're only mined once the base fee drops
+    /// below it.
+    High,
+    /// Batches are deferred while the base fee is above the maximum fee per
+    /// gas.
+    Low,
+}
+
+/// The kinds of batches sent by the sequencer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operation {
+    Insertion,
+    Deletion,
+}
+
+impl Operation {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Insertion => "insertion",
+            Self::Deletion => "deletion",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FeePolicy {
+    max_fee_per_gas:      Option<U256>,
+    priority_fee_per_gas: Option<U256>,
+    insertion_urgency:    Urgency,
+    deletion_urgency:     Urgency,
+}
+
+impl FeePolicy {
+    #[must_use]
+    pub fn new(
+        max_fee_per_gas_gwei: Option<u64>,
+        priority_fee_per_gas_gwei: Option<u64>,
+        insertion_urgency: Urgency,
+        deletion_urgency: Urgency,
+    ) -> Self {
+        let from_gwei = |gwei: u64| U256::from(gwei) * U256::from(GWEI);
+
+        Self {
+            max_fee_per_gas: max_fee_per_gas_gwei.map(from_gwei),
+            priority_fee_per_gas: priority_fee_per_gas_gwei.map(from_gwei),
+            insertion_urgency,
+            deletion_urgency,
+        }
+    }
+
+    #[must_use]
+    pub const fn urgency(&self, operation: Operation) -> Urgency {
+        match operation {
+            Operation::Insertion => self.insertion_urgency,
+            Operation::Deletion => self.deletion_urgency,
+        }
+    }
+
+    /// Whether batches of the `operation` should wait for the `base_fee` to
+    /// drop before they are sent.
+    #[must_use]
+    pub fn should_defer(&self, operation: Operation, base_fee: U256) -> bool {
+        match (self.urgency(operation), self.max_fee_per_gas) {
+            (Urgency::Low, Some(max_fee_per_gas)) => base_fee > max_fee_per_gas,
+            _ => false,
+        }
+    }
+
+    /// Limits a fee to the maximum fee per gas.
+    #[must_use]
+    pub fn cap(&self, fee: U256) -> U256 {
+        self.max_fee_per_gas.map_or(fee, |max_fee| fee.min(max_fee))
+    }
+
+    /// Sets the fees of the transaction. Fees that aren't configured are left
+    /// to be estimated when the transaction is filled.
+    ///
+    /// Once the max fee is known, the priority fee is limited to it, as nodes
+    /// reject transactions with a priority fee above their max fee.
+    pub fn apply(&self, tx: &mut TypedTransaction) {
+        if let TypedTransaction::Eip1559(inner) = tx {
+            if let Some(max_fee_per_gas) = self.max_fee_per_gas {
+                inner.max_fee_per_gas = Some(max_fee_per_gas);
+            }
+
+            if let Some(priority_fee_per_gas) = self.priority_fee_per_gas {
+                inner.max_priority_fee_per_gas = Some(self.cap(priority_fee_per_gas));
+            }
+
+            if let (Some(max_fee_per_gas), Some(priority_fee_per_gas)) =
+                (inner.max_fee_per_gas, inner.max_priority_fee_per_gas)
+            {
+                inner.max_priority_fee_per_gas = Some(priority_fee_per_gas.min(max_fee_per_gas));
+            }
+        } else if let Some(gas_price) = tx.gas_price() {
+            tx.set_gas_price(self.cap(gas_price));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::Eip1559TransactionRequest;
+
+    use super::*;
+
+    #[test]
+    fn defers_low_urgency_operations() {
+        let policy = FeePolicy::new(Some(50), None, Urgency::High, Urgency::Low);
+        let base_fee = U256::from(60) * U256::from(GWEI);
+
+        assert!(!policy.should_defer(Operation::Insertion, base_fee));
+        assert!(policy.should_defer(Operation::Deletion, base_fee));
+        assert!(!policy.should_defer(Operation::Deletion, U256::from(GWEI)));
+
+        let unlimited = FeePolicy::new(None, None, Urgency::Low, Urgency::Low);
+        assert!(!unlimited.should_defer(Operation::Deletion, base_fee));
+    }
+
+    #[test]
+    fn applies_fees() {
+        let policy = FeePolicy::new(Some(50), Some(100), Urgency::High, Urgency::Low);
+
+        let mut tx = TypedTransaction::Eip1559(Eip1559TransactionRequest::new());
+        policy.apply(&mut tx);
+
+        let TypedTransaction::Eip1559(inner) = tx else {
+            unreachable!()
+        };
+        let max_fee = U256::from(50) * U256::from(GWEI);
+        assert_eq!(inner.max_fee_per_gas, Some(max_fee));
+        assert_eq!(inner.max_priority_fee_per_gas, Some(max_fee));
+    }
+
+    #[test]
+    fn limits_priority_fee_to_estimated_max_fee() {
+        let policy = FeePolicy::new(None, Some(100), Urgency::High, Urgency::High);
+        let gwei = |gwei: u64| U256::from(gwei) * U256::from(GWEI);
+
+        // The max fee is left to be estimated when the transaction is filled
+        let mut tx = TypedTransaction::Eip1559(Eip1559TransactionRequest::new());
+        policy.apply(&mut tx);
+
+        let TypedTransaction::Eip1559(inner) = &mut tx else {
+            unreachable!()
+        };
+        assert_eq!(inner.max_fee_per_gas, None);
+        assert_eq!(inner.max_priority_fee_per_gas, Some(gwei(100)));
+
+        // Fees estimated when the transaction was filled
+        inner.max_fee_per_gas = Some(gwei(60));
+        policy.apply(&mut tx);
+
+        let TypedTransaction::Eip1559(inner) = tx else {
+            unreachable!()
+        };
+        assert_eq!(inner.max_fee_per_gas, Some(gwei(60)));
+        assert_eq!(inner.max_priority_fee_per_gas, Some(gwei(60)));
+    }
+}
@@ -1,37 +1,101 @@
 use std::collections::HashMap;
+use std::num::ParseIntError;
+use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::Result as AnyhowResult;
 use clap::Parser;
+use ethers::providers::Middleware;
 use ethers::types::transaction::eip2718::TypedTransaction;
-use ethers::types::Address;
-pub use read::{EventError, ReadProvider};
-use tracing::instrument;
+use ethers::types::{Address, BlockNumber};
+pub use fee::{FeePolicy, Operation, Urgency};
+pub use read::{EventError, ProviderUrls, ReadProvider};
+use tracing::{instrument, warn};
 use url::Url;
-pub use write::TxError;
+pub use write::{RevertError, TxError};
 
 use self::write::{TransactionId, WriteProvider};
+use crate::database::Database;
 use crate::serde_utils::JsonStrWrapper;
 
+pub mod fee;
 pub mod read;
 pub mod write;
 
 mod write_oz;
+mod write_signer;
+
+fn duration_from_str(value: &str) -> Result<Duration, ParseIntError> {
+    Ok(Duration::from_secs(u64::from_str(value)?))
+}
+
+/// How transactions are signed and sent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum WriteProviderKind {
+    /// Transactions are relayed through OpenZeppelin Defender.
+    Oz,
+    /// Transactions are signed with a local key and sent to the Ethereum
+    /// provider.
+    Local,
+    /// Transactions are signed by a remote signer, such as Web3Signer, and
+    /// sent to the Ethereum provider.
+    Remote,
+}
 
 // TODO: Log and metrics for signer / nonces.
 #[derive(Clone, Debug, PartialEq, Parser)]
 #[group(skip)]
 pub struct Options {
-    /// Ethereum API Provider
-    #[clap(long, env, default_value = "http://localhost:8545")]
-    pub ethereum_provider: Url,
-
-    /// Provider urls for the secondary chains
+    /// Ethereum API Providers, separated by commas. Requests fail over to the
+    /// next provider when one fails.
+    #[clap(
+        long,
+        env,
+        value_delimiter = ',',
+        default_value = "http://localhost:8545"
+    )]
+    pub ethereum_provider: Vec<Url>,
+
+    /// Provider urls for the secondary chains. Each chain has either a single
+    /// url or a list of urls.
     #[clap(long, env, default_value = "[]")]
-    pub secondary_providers: JsonStrWrapper<Vec<Url>>,
+    pub secondary_providers: JsonStrWrapper<Vec<ProviderUrls>>,
+
+    /// How many providers of a chain have to agree on critical reads, such as
+    /// the latest root. Capped at the number of providers of each secondary
+    /// chain.
+    #[clap(long, env, default_value = "1")]
+    pub provider_quorum: usize,
+
+    /// How transactions are sent, either `oz`, `local` or `remote`.
+    #[clap(long, env, value_enum, default_value = "oz")]
+    pub write_provider: WriteProviderKind,
+
+    /// The maximum fee per gas paid by transactions (in gwei). Low urgency
+    /// batches are deferred while the base fee is above it.
+    #[clap(long, env)]
+    pub max_fee_per_gas_gwei: Option<u64>,
+
+    /// The priority fee per gas paid to validators (in gwei). Estimated by the
+    /// provider when not set. The OpenZeppelin relayer only applies the fees
+    /// when both are set.
+    #[clap(long, env)]
+    pub priority_fee_per_gas_gwei: Option<u64>,
+
+    /// How urgently insertion batches are sent, either `high` or `low`.
+    #[clap(long, env, value_enum, default_value = "high")]
+    pub insertion_urgency: Urgency,
+
+    /// How urgently deletion batches are sent, either `high` or `low`.
+    #[clap(long, env, value_enum, default_value = "low")]
+    pub deletion_urgency: Urgency,
 
     #[clap(flatten)]
     pub write_options: write_oz::Options,
+
+    #[clap(flatten)]
+    pub signer_options: write_signer::Options,
 }
 
 #[derive(Clone, Debug)]
@@ -40,30 +104,76 @@ pub struct Ethereum {
     // Mapping of chain id to provider
     secondary_read_providers: HashMap<u64, Arc<ReadProvider>>,
     write_provider:           Arc<dyn WriteProvider>,
+    fee_policy:               FeePolicy,
 }
 
 impl Ethereum {
     #[instrument(name = "Ethereum::new", level = "debug", skip_all)]
-    pub async fn new(options: Options) -> AnyhowResult<Self> {
-        let read_provider = ReadProvider::new(options.ethereum_provider).await?;
+    pub async fn new(options: Options, database: Arc<Database>) -> AnyhowResult<Self> {
+        let read_provider =
+            ReadProvider::new(options.ethereum_provider, options.provider_quorum).await?;
 
         let mut secondary_read_providers = HashMap::new();
 
-        for secondary_url in &options.secondary_providers.0 {
-            let secondary_read_provider = ReadProvider::new(secondary_url.clone()).await?;
+        for secondary_urls in options.secondary_providers.0 {
+            let secondary_urls = secondary_urls.into_vec();
+            let quorum = options.provider_quorum.min(secondary_urls.len());
+            let secondary_read_provider = ReadProvider::new(secondary_urls, quorum).await?;
             secondary_read_providers.insert(
                 secondary_read_provider.chain_id.as_u64(),
                 Arc::new(secondary_read_provider),
             );
         }
 
-        let write_provider: Arc<dyn WriteProvider> =
-            Arc::new(write_oz::Provider::new(read_provider.clone(), &options.write_options).await?);
+        let fee_policy = FeePolicy::new(
+            options.max_fee_per_gas_gwei,
+            options.priority_fee_per_gas_gwei,
+            options.insertion_urgency,
+            options.deletion_urgency,
+        );
+
+        let write_provider: Arc<dyn WriteProvider> = match options.write_provider {
+            WriteProviderKind::Oz => Arc::new(
+                write_oz::Provider::new(read_provider.clone(), &options.write_options).await?,
+            ),
+            WriteProviderKind::Local => {
+                let signer = write_signer::LocalSigner::new(
+                    &options.signer_options,
+                    read_provider.chain_id.as_u64(),
+                )?;
+
+                Arc::new(
+                    write_signer::Provider::new(
+                        read_provider.clone(),
+                        database,
+                        Arc::new(signer),
+                        fee_policy.clone(),
+                        &options.signer_options,
+                    )
+                    .await?,
+                )
+            }
+            WriteProviderKind::Remote => {
+                let signer = write_signer::RemoteSigner::new(&options.signer_options)?;
+
+                Arc::new(
+                    write_signer::Provider::new(
+                        read_provider.clone(),
+                        database,
+                        Arc::new(signer),
+                        fee_policy.clone(),
+                        &options.signer_options,
+                    )
+                    .await?,
+                )
+            }
+        };
 
         Ok(Self {
             read_provider: Arc::new(read_provider),
             secondary_read_providers,
             write_provider,
+            fee_policy,
         })
     }
 
@@ -82,11 +192,53 @@ impl Ethereum {
         self.write_provider.address()
     }
 
+    /// Whether batches of the `operation` should wait for the base fee to
+    /// drop before they are sent.
+    pub async fn should_defer(&self, operation: Operation) -> AnyhowResult<bool> {
+        if self.fee_policy.urgency(operation) == Urgency::High {
+            return Ok(false);
+        }
+
+        let Some(base_fee) = self.read_provider.base_fee_per_gas().await? else {
+            return Ok(false);
+        };
+
+        Ok(self.fee_policy.should_defer(operation, base_fee))
+    }
+
+    /// Executes the transaction against the latest block without sending it,
+    /// to find out whether it would revert.
+    pub async fn simulate_transaction(&self, tx: &TypedTransaction) -> Result<(), TxError> {
+        let mut tx = tx.clone();
+        tx.set_from(self.address());
+
+        let Err(error) = self
+            .read_provider
+            .call(&tx, Some(BlockNumber::Latest.into()))
+            .await
+        else {
+            return Ok(());
+        };
+
+        match read::revert_data(&error) {
+            Some(data) => {
+                let reason = RevertError::decode(&data);
+                warn!(%reason, "Transaction would revert");
+
+                Err(TxError::Revert(reason))
+            }
+            None => Err(TxError::Simulation(Box::new(error))),
+        }
+    }
+
     pub async fn send_transaction(
         &self,
         tx: TypedTransaction,
         only_once: bool,
     ) -> Result<TransactionId, TxError> {
+        let mut tx = tx;
+        self.fee_policy.apply(&mut tx);
+
         self.write_provider.send_transaction(tx, only_once).await
     }
 
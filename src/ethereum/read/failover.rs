@@ -0,0 +1,239 @@
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use ::prometheus::{register_histogram_vec, register_int_counter_vec, HistogramVec, IntCounterVec};
+use async_trait::async_trait;
+use ethers::providers::JsonRpcClient;
+use once_cell::sync::Lazy;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::sync::OnceCell;
+use tracing::warn;
+use url::Url;
+
+use super::rpc_logger::{ClassifyError, RpcErrorKind};
+use super::transport::{Transport, TransportError};
+
+static ENDPOINT_LATENCY: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        "eth_rpc_endpoint_latency_seconds",
+        "The latency of each Ethereum provider endpoint in seconds.",
+        &["endpoint"]
+    )
+    .unwrap()
+});
+static ENDPOINT_ERRORS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "eth_rpc_endpoint_errors",
+        "Number of failed requests to each Ethereum provider endpoint.",
+        &["endpoint"]
+    )
+    .unwrap()
+});
+
+/// A single Ethereum provider endpoint. It connects when it's first used, and a
+/// failed connection is tried again by the next request, so that a provider
+/// that is down at startup is used once it's back.
+#[derive(Clone, Debug)]
+pub struct Endpoint {
+    /// The host of the endpoint. Provider urls often contain API keys, so
+    /// only the host is logged.
+    pub name:  String,
+    url:       Url,
+    transport: Arc<OnceCell<Transport>>,
+}
+
+impl Endpoint {
+    #[must_use]
+    pub fn new(url: Url) -> Self {
+        let name = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{host}:{port}"),
+            (Some(host), None) => host.to_string(),
+            (None, _) => url.path().to_string(),
+        };
+
+        Self {
+            name,
+            url,
+            transport: Arc::new(OnceCell::new()),
+        }
+    }
+
+    /// The transport of the endpoint, connecting to it if it isn't yet.
+    pub async fn transport(&self) -> Result<&Transport, TransportError> {
+        self.transport
+            .get_or_try_init(|| Transport::new(self.url.clone()))
+            .await
+    }
+}
+
+#[async_trait]
+impl JsonRpcClient for Endpoint {
+    type Error = TransportError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned,
+    {
+        self.transport().await?.request(method, params).await
+    }
+}
+
+/// Sends requests to the current endpoint, and moves on to the next endpoint
+/// when a request fails, including when it's rate limited or times out.
+///
+/// Errors returned by the node itself, such as reverted calls, are returned as
+/// they are, because every endpoint would return them.
+#[derive(Clone, Debug)]
+pub struct FailoverTransport {
+    endpoints: Arc<[Endpoint]>,
+    current:   Arc<AtomicUsize>,
+}
+
+impl FailoverTransport {
+    /// # Panics
+    ///
+    /// Panics if there are no `endpoints`.
+    #[must_use]
+    pub fn new(endpoints: Vec<Endpoint>) -> Self {
+        assert!(!endpoints.is_empty(), "At least one endpoint is required");
+
+        Self {
+            endpoints: endpoints.into(),
+            current:   Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    #[must_use]
+    pub fn endpoints(&self) -> &[Endpoint] {
+        &self.endpoints
+    }
+}
+
+#[async_trait]
+impl JsonRpcClient for FailoverTransport {
+    type Error = TransportError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned,
+    {
+        let start = self.current.load(Ordering::Relaxed);
+        let mut last_error = None;
+
+        for attempt in 0..self.endpoints.len() {
+            let index = (start + attempt) % self.endpoints.len();
+            let endpoint = &self.endpoints[index];
+
+            let timer = ENDPOINT_LATENCY
+                .with_label_values(&[&endpoint.name])
+                .start_timer();
+            let result = endpoint.request(method, &params).await;
+            timer.observe_duration();
+
+            match result {
+                Ok(response) => return Ok(response),
+                Err(error) if error.classify() == RpcErrorKind::Node => return Err(error),
+                Err(error) => {
+                    ENDPOINT_ERRORS.with_label_values(&[&endpoint.name]).inc();
+
+                    let next = (index + 1) % self.endpoints.len();
+                    warn!(
+                        endpoint = %endpoint.name,
+                        next_endpoint = %self.endpoints[next].name,
+                        method,
+                        ?error,
+                        "Ethereum provider request failed, failing over"
+                    );
+
+                    // Other requests may have moved on already
+                    let _ = self.current.compare_exchange(
+                        index,
+                        next,
+                        Ordering::Relaxed,
+                        Ordering::Relaxed,
+                    );
+                    last_error = Some(error);
+                }
+            }
+        }
+
+        Err(last_error.expect("there is at least one endpoint"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv4Addr, SocketAddr, TcpListener};
+
+    use axum::routing::post;
+    use axum::{Json, Router};
+    use ethers::types::U64;
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    /// Answers every JSON-RPC request with `response`, which holds either a
+    /// result or an error.
+    fn spawn_endpoint(response: Value) -> anyhow::Result<Endpoint> {
+        let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))?;
+        let url = Url::parse(&format!("http://{}", listener.local_addr()?))?;
+
+        let app = Router::new().route(
+            "/",
+            post(move |Json(request): Json<Value>| {
+                let mut response = response.clone();
+                async move {
+                    response["jsonrpc"] = json!("2.0");
+                    response["id"] = request["id"].clone();
+                    Json(response)
+                }
+            }),
+        );
+
+        let server = axum::Server::from_tcp(listener)?.serve(app.into_make_service());
+        tokio::spawn(server);
+
+        Ok(Endpoint::new(url))
+    }
+
+    #[tokio::test]
+    async fn rate_limited_requests_fail_over() -> anyhow::Result<()> {
+        let rate_limited = spawn_endpoint(json!({
+            "error": { "code": -32005, "message": "limit exceeded" }
+        }))?;
+        let available = spawn_endpoint(json!({ "result": "0x2a" }))?;
+
+        let failover = FailoverTransport::new(vec![rate_limited, available]);
+
+        let block_number: U64 = failover.request("eth_blockNumber", ()).await?;
+        assert_eq!(block_number, U64::from(42));
+
+        // The next requests start with the available endpoint
+        assert_eq!(failover.current.load(Ordering::Relaxed), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn node_errors_are_returned_without_failing_over() -> anyhow::Result<()> {
+        let reverting = spawn_endpoint(json!({
+            "error": { "code": 3, "message": "execution reverted" }
+        }))?;
+        let available = spawn_endpoint(json!({ "result": "0x2a" }))?;
+
+        let failover = FailoverTransport::new(vec![reverting, available]);
+
+        let error = failover
+            .request::<_, U64>("eth_call", ())
+            .await
+            .expect_err("the call reverts");
+        assert_eq!(error.classify(), RpcErrorKind::Node);
+        assert_eq!(failover.current.load(Ordering::Relaxed), 0);
+
+        Ok(())
+    }
+}
@@ -1,44 +1,107 @@
-use anyhow::{anyhow, Result as AnyhowResult};
+use std::collections::HashMap;
+
+use anyhow::{anyhow, ensure, Result as AnyhowResult};
 use chrono::{Duration as ChronoDuration, Utc};
-use ethers::abi::Error as AbiError;
-use ethers::providers::{Middleware, Provider};
-use ethers::types::{BlockId, BlockNumber, Chain, U256};
+use ethers::abi::{Detokenize, Error as AbiError};
+use ethers::contract::ContractCall;
+use ethers::providers::{
+    HttpClientError, IpcError, JsonRpcError, Middleware, Provider, ProviderError, WsClientError,
+};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{BlockId, BlockNumber, Bytes, Chain, U256};
+use futures::future::join_all;
 use futures::{try_join, FutureExt};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 use url::Url;
 
+use self::failover::{Endpoint, FailoverTransport};
 use self::rpc_logger::RpcLogger;
-use self::transport::Transport;
+use self::transport::TransportError;
 
+pub mod failover;
 pub mod rpc_logger;
 pub mod transport;
+pub mod ws;
+
+type InnerProvider = Provider<RpcLogger<FailoverTransport>>;
+type EndpointProvider = Provider<RpcLogger<Endpoint>>;
 
-type InnerProvider = Provider<RpcLogger<Transport>>;
+/// The urls of the providers of a chain, either a single url or a list.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ProviderUrls {
+    One(Url),
+    Many(Vec<Url>),
+}
+
+impl ProviderUrls {
+    #[must_use]
+    pub fn into_vec(self) -> Vec<Url> {
+        match self {
+            Self::One(url) => vec![url],
+            Self::Many(urls) => urls,
+        }
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct ReadProvider {
     inner:        InnerProvider,
+    /// Every endpoint on its own, to check that they agree on critical reads.
+    endpoints:    Vec<(String, EndpointProvider)>,
+    quorum:       usize,
     pub chain_id: U256,
     pub legacy:   bool,
 }
 
 impl ReadProvider {
-    pub async fn new(url: Url) -> AnyhowResult<Self> {
-        // Connect to the Ethereum provider
-        // TODO: Allow multiple providers with failover / broadcast.
+    /// Connects to the providers at `urls`, which must all serve the same
+    /// chain. Requests fail over to the next provider when one fails, and
+    /// critical reads made with [`ReadProvider::quorum_call`] require
+    /// `quorum` providers to agree.
+    pub async fn new(urls: Vec<Url>, quorum: usize) -> AnyhowResult<Self> {
         // TODO: Requests don't seem to process in parallel. Check if this is
         // a limitation client side or server side.
-        // TODO: Does the WebSocket impl handle dropped connections by
-        // reconnecting? What is the timeout on stalled connections? What is
-        // the retry policy?
-        let (provider, chain_id, eip1559) = {
+        // TODO: What is the timeout on stalled WebSocket connections?
+        ensure!(
+            (1..=urls.len()).contains(&quorum),
+            "A quorum of {quorum} requires as many providers, but {} are configured",
+            urls.len()
+        );
+
+        let mut endpoints = Vec::with_capacity(urls.len());
+        for url in urls {
             info!(
                 provider = %url,
                 "Connecting to provider"
             );
-            let transport = Transport::new(url).await?;
-            let logger = RpcLogger::new(transport);
+
+            // A provider that is down at startup is kept, and connected once
+            // it's back
+            let endpoint = Endpoint::new(url);
+            match endpoint.transport().await {
+                Ok(_) => {}
+                Err(error @ TransportError::InvalidScheme(_)) => return Err(error.into()),
+                Err(error) => error!(?error, "Failed to connect to provider"),
+            }
+
+            endpoints.push(endpoint);
+        }
+
+        let failover = FailoverTransport::new(endpoints);
+        let endpoints = failover
+            .endpoints()
+            .iter()
+            .map(|endpoint| {
+                let provider = Provider::new(RpcLogger::new(endpoint.clone()));
+                (endpoint.name.clone(), provider)
+            })
+            .collect::<Vec<_>>();
+
+        let (provider, chain_id, eip1559) = {
+            let logger = RpcLogger::new(failover);
             let provider = Provider::new(logger);
 
             // Fetch state of the chain.
@@ -51,6 +114,17 @@ impl ReadProvider {
                     .map(|r| Ok(r.is_ok()))
             )?;
 
+            // Every provider must serve the same chain
+            for (name, endpoint) in &endpoints {
+                match endpoint.get_chainid().await {
+                    Ok(endpoint_chain_id) => ensure!(
+                        endpoint_chain_id == chain_id,
+                        "Provider {name} serves chain {endpoint_chain_id} instead of {chain_id}"
+                    ),
+                    Err(error) => warn!(endpoint = %name, ?error, "Failed to check chain id"),
+                }
+            }
+
             // Identify chain.
             let chain = Chain::try_from(chain_id)
                 .map_or_else(|_| "Unknown".to_string(), |chain| chain.to_string());
@@ -84,10 +158,74 @@ impl ReadProvider {
 
         Ok(Self {
             inner: provider,
+            endpoints,
+            quorum,
             chain_id,
             legacy: !eip1559,
         })
     }
+
+    /// Calls the contract, requiring the configured quorum of providers to
+    /// return the same result.
+    pub async fn quorum_call<D: Detokenize>(&self, call: ContractCall<Self, D>) -> AnyhowResult<D> {
+        let output = self.call_with_quorum(&call.tx, call.block).await?;
+        let tokens = call.function.decode_output(&output)?;
+
+        Ok(D::from_tokens(tokens)?)
+    }
+
+    async fn call_with_quorum(
+        &self,
+        tx: &TypedTransaction,
+        block: Option<BlockId>,
+    ) -> AnyhowResult<Bytes> {
+        if self.quorum == 1 {
+            return Ok(self.call(tx, block).await?);
+        }
+
+        let results = join_all(
+            self.endpoints
+                .iter()
+                .map(|(_, endpoint)| endpoint.call(tx, block)),
+        )
+        .await;
+
+        let mut votes: HashMap<Vec<u8>, usize> = HashMap::new();
+        for ((name, _), result) in self.endpoints.iter().zip(results) {
+            match result {
+                Ok(output) => *votes.entry(output.to_vec()).or_default() += 1,
+                Err(error) => warn!(endpoint = %name, ?error, "Provider failed quorum call"),
+            }
+        }
+
+        let (output, count) = votes
+            .into_iter()
+            .max_by_key(|(_, count)| *count)
+            .ok_or_else(|| anyhow!("All providers failed the call"))?;
+
+        ensure!(
+            count >= self.quorum,
+            "Only {count} providers agree on the result, but {} are required",
+            self.quorum
+        );
+
+        Ok(output.into())
+    }
+
+    /// The base fee per gas of the latest block, or `None` on chains without
+    /// EIP-1559.
+    pub async fn base_fee_per_gas(&self) -> AnyhowResult<Option<U256>> {
+        if self.legacy {
+            return Ok(None);
+        }
+
+        let latest_block = self
+            .get_block(BlockNumber::Latest)
+            .await?
+            .ok_or_else(|| anyhow!("Failed to get latest block from Ethereum provider"))?;
+
+        Ok(latest_block.base_fee_per_gas)
+    }
 }
 
 impl Middleware for ReadProvider {
@@ -100,6 +238,29 @@ impl Middleware for ReadProvider {
     }
 }
 
+/// Extracts the data returned by a reverted `eth_call` or `eth_estimateGas`,
+/// if the provider included it in the error.
+#[must_use]
+pub fn revert_data(error: &ProviderError) -> Option<Bytes> {
+    let ProviderError::JsonRpcClientError(error) = error else {
+        return None;
+    };
+
+    let json_rpc_error: &JsonRpcError = if let Some(HttpClientError::JsonRpcError(error)) =
+        error.downcast_ref::<HttpClientError>()
+    {
+        error
+    } else if let Some(WsClientError::JsonRpcError(error)) = error.downcast_ref::<WsClientError>() {
+        error
+    } else if let Some(IpcError::JsonRpcError(error)) = error.downcast_ref::<IpcError>() {
+        error
+    } else {
+        return None;
+    };
+
+    serde_json::from_value(json_rpc_error.data.clone()?).ok()
+}
+
 #[derive(Debug, Error)]
 pub enum EventError {
     #[error("Error parsing log event: {0}")]
@@ -1,12 +1,15 @@
 use std::fmt::Debug;
 
-use ::prometheus::{register_histogram, register_int_counter_vec, Histogram, IntCounterVec};
+use ::prometheus::{register_histogram_vec, register_int_counter_vec, HistogramVec, IntCounterVec};
 use async_trait::async_trait;
-use ethers::providers::JsonRpcClient;
+use ethers::providers::{HttpClientError, IpcError, JsonRpcClient, JsonRpcError, WsClientError};
 use once_cell::sync::Lazy;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
-use tracing::instrument;
+use tracing::field::Empty;
+use tracing::{instrument, Span};
+
+use super::transport::TransportError;
 
 static REQUESTS: Lazy<IntCounterVec> = Lazy::new(|| {
     register_int_counter_vec!(
@@ -16,14 +19,95 @@ static REQUESTS: Lazy<IntCounterVec> = Lazy::new(|| {
     )
     .unwrap()
 });
-static LATENCY: Lazy<Histogram> = Lazy::new(|| {
-    register_histogram!(
+static LATENCY: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
         "eth_rpc_latency_seconds",
-        "The Ethereum provider latency in seconds."
+        "The Ethereum provider latency in seconds by method.",
+        &["method"]
+    )
+    .unwrap()
+});
+static ERRORS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "eth_rpc_errors",
+        "Number of failed Ethereum provider requests by method and kind of error.",
+        &["method", "kind"]
     )
     .unwrap()
 });
 
+/// JSON-RPC error code used by most providers when a rate limit is exceeded.
+const LIMIT_EXCEEDED: i64 = -32005;
+
+/// Why a JSON-RPC request failed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RpcErrorKind {
+    /// The provider rejected the request because of its rate limit.
+    RateLimit,
+    /// The request timed out.
+    Timeout,
+    /// The node executed the request and returned an error, e.g. a revert.
+    Node,
+    /// The request didn't reach the node, or the response couldn't be read.
+    Transport,
+}
+
+impl RpcErrorKind {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::RateLimit => "rate_limit",
+            Self::Timeout => "timeout",
+            Self::Node => "node_error",
+            Self::Transport => "transport",
+        }
+    }
+
+    fn from_message(message: &str) -> Option<Self> {
+        let message = message.to_lowercase();
+
+        if message.contains("rate limit") || message.contains("too many requests") {
+            Some(Self::RateLimit)
+        } else if message.contains("timeout") || message.contains("timed out") {
+            Some(Self::Timeout)
+        } else {
+            None
+        }
+    }
+
+    fn from_json_rpc_error(error: &JsonRpcError) -> Self {
+        if error.code == LIMIT_EXCEEDED {
+            return Self::RateLimit;
+        }
+
+        Self::from_message(&error.message).unwrap_or(Self::Node)
+    }
+}
+
+/// Errors of a JSON-RPC client that can be classified for metrics.
+pub trait ClassifyError {
+    fn classify(&self) -> RpcErrorKind;
+}
+
+impl ClassifyError for TransportError {
+    fn classify(&self) -> RpcErrorKind {
+        match self {
+            Self::Http(HttpClientError::JsonRpcError(error))
+            | Self::Ws(WsClientError::JsonRpcError(error))
+            | Self::Ipc(IpcError::JsonRpcError(error)) => RpcErrorKind::from_json_rpc_error(error),
+            Self::Http(HttpClientError::ReqwestError(error)) if error.is_timeout() => {
+                RpcErrorKind::Timeout
+            }
+            // Rate limited requests are often answered with a plain HTTP error
+            // instead of a JSON-RPC response
+            Self::Http(HttpClientError::SerdeJson { text, .. }) => {
+                RpcErrorKind::from_message(text).unwrap_or(RpcErrorKind::Transport)
+            }
+            _ => RpcErrorKind::Transport,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct RpcLogger<Inner> {
     inner: Inner,
@@ -39,20 +123,72 @@ impl<Inner> RpcLogger<Inner> {
 impl<Inner> JsonRpcClient for RpcLogger<Inner>
 where
     Inner: JsonRpcClient + 'static,
-    <Inner as JsonRpcClient>::Error: Sync + Send + 'static,
+    <Inner as JsonRpcClient>::Error: ClassifyError + Sync + Send + 'static,
 {
     type Error = Inner::Error;
 
-    #[instrument(name = "eth_rpc", level = "debug", skip(self))]
+    #[instrument(
+        name = "eth_rpc",
+        level = "info",
+        skip(self, params),
+        fields(
+            otel.kind = "client",
+            rpc.system = "jsonrpc",
+            rpc.method = method,
+            otel.status_code = Empty,
+            error.kind = Empty,
+        )
+    )]
     async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
     where
         T: Debug + Serialize + Send + Sync,
         R: DeserializeOwned,
     {
         REQUESTS.with_label_values(&[method]).inc();
-        let timer = LATENCY.start_timer();
+        let timer = LATENCY.with_label_values(&[method]).start_timer();
         let result = self.inner.request(method, params).await;
         timer.observe_duration();
+
+        if let Err(error) = &result {
+            let kind = error.classify().as_str();
+            ERRORS.with_label_values(&[method, kind]).inc();
+
+            let span = Span::current();
+            span.record("otel.status_code", "ERROR");
+            span.record("error.kind", kind);
+        }
+
         result
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_json_rpc_errors() {
+        let error = |code, message: &str| JsonRpcError {
+            code,
+            message: message.to_string(),
+            data: None,
+        };
+
+        assert_eq!(
+            RpcErrorKind::from_json_rpc_error(&error(LIMIT_EXCEEDED, "limit exceeded")),
+            RpcErrorKind::RateLimit
+        );
+        assert_eq!(
+            RpcErrorKind::from_json_rpc_error(&error(-32000, "Too Many Requests")),
+            RpcErrorKind::RateLimit
+        );
+        assert_eq!(
+            RpcErrorKind::from_json_rpc_error(&error(-32000, "request timed out")),
+            RpcErrorKind::Timeout
+        );
+        assert_eq!(
+            RpcErrorKind::from_json_rpc_error(&error(3, "execution reverted")),
+            RpcErrorKind::Node
+        );
+    }
+}
@@ -7,12 +7,14 @@ use serde::Serialize;
 use thiserror::Error;
 use url::Url;
 
+use super::ws::ReconnectingWs;
+
 // Todo: Enable IPC or WS based on feature flags
 
 #[derive(Debug, Clone)]
 pub enum Transport {
     Http(Http),
-    Ws(Ws),
+    Ws(ReconnectingWs),
     Ipc(Ipc),
 }
 
@@ -36,7 +38,9 @@ impl Transport {
         match url.scheme() {
             "http" | "https" => Ok(Self::Http(Http::new(url))),
             "ws" | "wss" => Ok(Self::Ws(
-                Ws::connect(url).await.map_err(TransportError::Ws)?,
+                ReconnectingWs::connect(url)
+                    .await
+                    .map_err(TransportError::Ws)?,
             )),
             "ipc" if url.host().is_none() => Ok(Self::Ipc(
                 Ipc::connect(url.path())
@@ -0,0 +1,241 @@
+use std::fmt::Debug;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use ::prometheus::{register_int_counter, IntCounter};
+use async_trait::async_trait;
+use ethers::providers::{JsonRpcClient, Ws, WsClientError};
+use once_cell::sync::Lazy;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::sync::Mutex;
+use tracing::{error, info, warn};
+use url::Url;
+
+/// How long to wait before the first reconnection attempt. The delay doubles
+/// with every failed attempt.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// How many times to try to reconnect before failing the requests waiting for
+/// the connection.
+const MAX_RECONNECT_ATTEMPTS: u32 = 10;
+/// How many times a request is re-sent over a new connection.
+const MAX_RESENDS: usize = 3;
+/// How long requests fail right away after reconnecting gave up, instead of
+/// trying to reconnect again.
+const RECONNECT_FAILURE_COOLDOWN: Duration = MAX_BACKOFF;
+
+static RECONNECTS: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "eth_ws_reconnects",
+        "Number of times the WebSocket connection to the Ethereum provider was re-established."
+    )
+    .unwrap()
+});
+static RECONNECT_FAILURES: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "eth_ws_reconnect_failures",
+        "Number of failed attempts to re-establish the WebSocket connection to the Ethereum \
+         provider."
+    )
+    .unwrap()
+});
+
+/// A WebSocket transport that reconnects when the connection drops.
+///
+/// Requests that fail because of the dropped connection, including the ones
+/// that were in flight, are re-sent over the new connection. Subscriptions are
+/// not restored.
+#[derive(Clone, Debug)]
+pub struct ReconnectingWs {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    url:        Url,
+    connection: RwLock<Connection>,
+    /// Held while reconnecting, so that requests that fail at the same time
+    /// reconnect only once. Holds the time at which reconnecting last gave up,
+    /// so that the requests that were waiting for it fail fast.
+    reconnect:  Mutex<Option<Instant>>,
+}
+
+#[derive(Clone, Debug)]
+struct Connection {
+    ws:         Ws,
+    /// Incremented on every reconnection.
+    generation: u64,
+}
+
+impl ReconnectingWs {
+    pub async fn connect(url: Url) -> Result<Self, WsClientError> {
+        let ws = Ws::connect(url.clone()).await?;
+
+        Ok(Self {
+            inner: Arc::new(Inner {
+                url,
+                connection: RwLock::new(Connection { ws, generation: 0 }),
+                reconnect: Mutex::new(None),
+            }),
+        })
+    }
+
+    fn connection(&self) -> Connection {
+        self.inner
+            .connection
+            .read()
+            .expect("connection lock is never poisoned")
+            .clone()
+    }
+
+    /// Replaces the connection of the given `generation`, unless another
+    /// request replaced it already.
+    async fn reconnect(&self, generation: u64) -> Result<(), WsClientError> {
+        let mut failed_at = self.inner.reconnect.lock().await;
+
+        if self.connection().generation != generation {
+            return Ok(());
+        }
+
+        if failed_at.map_or(false, |failed_at| {
+            failed_at.elapsed() < RECONNECT_FAILURE_COOLDOWN
+        }) {
+            return Err(WsClientError::UnexpectedClose);
+        }
+
+        let mut attempt = 0;
+        loop {
+            tokio::time::sleep(backoff(attempt)).await;
+
+            match Ws::connect(self.inner.url.clone()).await {
+                Ok(ws) => {
+                    *self
+                        .inner
+                        .connection
+                        .write()
+                        .expect("connection lock is never poisoned") = Connection {
+                        ws,
+                        generation: generation + 1,
+                    };
+
+                    *failed_at = None;
+
+                    RECONNECTS.inc();
+                    info!(attempt, "Reconnected to the Ethereum provider");
+
+                    return Ok(());
+                }
+                Err(error) => {
+                    RECONNECT_FAILURES.inc();
+                    attempt += 1;
+
+                    if attempt == MAX_RECONNECT_ATTEMPTS {
+                        error!(?error, "Failed to reconnect to the Ethereum provider");
+                        *failed_at = Some(Instant::now());
+                        return Err(error);
+                    }
+
+                    warn!(
+                        attempt,
+                        ?error,
+                        "Failed to reconnect to the Ethereum provider, retrying"
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Whether the request failed because of the connection, rather than because
+/// of the request itself.
+const fn is_connection_error(error: &WsClientError) -> bool {
+    !matches!(
+        error,
+        WsClientError::JsonRpcError(_) | WsClientError::JsonError(_)
+    )
+}
+
+fn backoff(attempt: u32) -> Duration {
+    INITIAL_BACKOFF
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(MAX_BACKOFF)
+}
+
+#[async_trait]
+impl JsonRpcClient for ReconnectingWs {
+    type Error = WsClientError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned,
+    {
+        let mut resends = 0;
+
+        loop {
+            let connection = self.connection();
+
+            match connection.ws.request(method, &params).await {
+                Err(error) if is_connection_error(&error) && resends < MAX_RESENDS => {
+                    warn!(
+                        method,
+                        ?error,
+                        "WebSocket request failed, reconnecting and re-sending"
+                    );
+
+                    self.reconnect(connection.generation).await?;
+                    resends += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::U64;
+    use ethers::utils::Anvil;
+
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_the_maximum() {
+        assert_eq!(backoff(0), INITIAL_BACKOFF);
+        assert_eq!(backoff(3), INITIAL_BACKOFF * 8);
+        assert_eq!(backoff(20), MAX_BACKOFF);
+        assert_eq!(backoff(u32::MAX), MAX_BACKOFF);
+    }
+
+    #[tokio::test]
+    async fn requests_are_resent_after_reconnecting() {
+        let anvil = Anvil::new().spawn();
+        let port = anvil.port();
+        let ws = ReconnectingWs::connect(anvil.ws_endpoint().parse().unwrap())
+            .await
+            .unwrap();
+
+        let _: U64 = ws.request("eth_blockNumber", ()).await.unwrap();
+
+        drop(anvil);
+        let _anvil = Anvil::new().port(port).spawn();
+
+        let _: U64 = ws.request("eth_blockNumber", ()).await.unwrap();
+        assert_eq!(ws.connection().generation, 1);
+    }
+
+    #[tokio::test]
+    async fn requests_fail_fast_after_reconnecting_gave_up() {
+        let anvil = Anvil::new().spawn();
+        let ws = ReconnectingWs::connect(anvil.ws_endpoint().parse().unwrap())
+            .await
+            .unwrap();
+
+        drop(anvil);
+        *ws.inner.reconnect.lock().await = Some(Instant::now());
+
+        let result = tokio::time::timeout(INITIAL_BACKOFF, ws.reconnect(0)).await;
+        assert!(matches!(result, Ok(Err(WsClientError::UnexpectedClose))));
+    }
+}
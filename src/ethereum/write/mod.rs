@@ -7,6 +7,10 @@ use ethers::types::transaction::eip2718::TypedTransaction;
 use ethers::types::{Address, TransactionReceipt, H256};
 use thiserror::Error;
 
+pub use self::revert::RevertError;
+
+mod revert;
+
 #[derive(Clone, Debug)]
 pub struct TransactionId(pub String);
 
@@ -51,6 +55,12 @@ pub enum TxError {
 
     #[error("Error parsing transaction id: {0}")]
     Parse(Box<dyn Error + Send + Sync + 'static>),
+
+    #[error("Error simulating transaction: {0}")]
+    Simulation(Box<dyn Error + Send + Sync + 'static>),
+
+    #[error("Transaction would revert: {0}")]
+    Revert(RevertError),
 }
 
 #[async_trait]
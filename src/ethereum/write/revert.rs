@@ -0,0 +1,139 @@
+//! Decoding of the reasons a call to the identity manager reverted.
+
+use ethers::abi::{self, ParamType, Token};
+use ethers::types::{Address, Bytes, U256};
+use ethers::utils::id;
+use thiserror::Error;
+
+/// Why a call to the identity manager contract reverted.
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub enum RevertError {
+    /// The batch was built on top of a root that is no longer the latest.
+    #[error("pre root {provided_root:#x} is not the latest root {latest_root:#x}")]
+    NotLatestRoot {
+        provided_root: U256,
+        latest_root:   U256,
+    },
+
+    /// The verifier rejected the proof, e.g. because it was computed for a
+    /// different start index.
+    #[error("proof validation failed")]
+    ProofValidationFailure,
+
+    #[error("element {element:#x} of type {element_type} is not in reduced form")]
+    UnreducedElement {
+        element_type: u8,
+        element:      U256,
+    },
+
+    #[error("invalid commitment at index {0}")]
+    InvalidCommitment(U256),
+
+    #[error("invalid deletion indices")]
+    InvalidDeletionIndices,
+
+    #[error("unsupported tree depth {0}")]
+    UnsupportedTreeDepth(u8),
+
+    #[error("{0:?} is not authorized")]
+    Unauthorized(Address),
+
+    /// The contract reverted with a `require` message.
+    #[error("{0}")]
+    Message(String),
+
+    #[error("unknown revert data {0}")]
+    Unknown(Bytes),
+}
+
+impl RevertError {
+    /// Decodes the data returned by a reverted call.
+    #[must_use]
+    pub fn decode(data: &Bytes) -> Self {
+        Self::try_decode(data).unwrap_or_else(|| Self::Unknown(data.clone()))
+    }
+
+    fn try_decode(data: &[u8]) -> Option<Self> {
+        if data.len() < 4 {
+            return None;
+        }
+        let (selector, arguments) = data.split_at(4);
+        let decode = |types: &[ParamType]| abi::decode(types, arguments).ok();
+
+        let error = if selector == id("NotLatestRoot(uint256,uint256)") {
+            let tokens = decode(&[ParamType::Uint(256), ParamType::Uint(256)])?;
+            Self::NotLatestRoot {
+                provided_root: tokens[0].clone().into_uint()?,
+                latest_root:   tokens[1].clone().into_uint()?,
+            }
+        } else if selector == id("ProofValidationFailure()") {
+            Self::ProofValidationFailure
+        } else if selector == id("UnreducedElement(uint8,uint256)") {
+            let tokens = decode(&[ParamType::Uint(8), ParamType::Uint(256)])?;
+            Self::UnreducedElement {
+                element_type: tokens[0].clone().into_uint()?.low_u32().try_into().ok()?,
+                element:      tokens[1].clone().into_uint()?,
+            }
+        } else if selector == id("InvalidCommitment(uint256)") {
+            let tokens = decode(&[ParamType::Uint(256)])?;
+            Self::InvalidCommitment(tokens[0].clone().into_uint()?)
+        } else if selector == id("InvalidDeletionIndices()") {
+            Self::InvalidDeletionIndices
+        } else if selector == id("UnsupportedTreeDepth(uint8)") {
+            let tokens = decode(&[ParamType::Uint(8)])?;
+            Self::UnsupportedTreeDepth(tokens[0].clone().into_uint()?.low_u32().try_into().ok()?)
+        } else if selector == id("Unauthorized(address)") {
+            let tokens = decode(&[ParamType::Address])?;
+            Self::Unauthorized(tokens[0].clone().into_address()?)
+        } else if selector == id("Error(string)") {
+            let tokens = decode(&[ParamType::String])?;
+            Self::Message(tokens.into_iter().next().and_then(Token::into_string)?)
+        } else {
+            return None;
+        };
+
+        Some(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(signature: &str, tokens: &[Token]) -> Bytes {
+        [&id(signature)[..], &abi::encode(tokens)[..]]
+            .concat()
+            .into()
+    }
+
+    #[test]
+    fn decodes_known_errors() {
+        let data = encode("NotLatestRoot(uint256,uint256)", &[
+            Token::Uint(1.into()),
+            Token::Uint(2.into()),
+        ]);
+        assert_eq!(RevertError::decode(&data), RevertError::NotLatestRoot {
+            provided_root: 1.into(),
+            latest_root:   2.into(),
+        });
+
+        let data = encode("ProofValidationFailure()", &[]);
+        assert_eq!(
+            RevertError::decode(&data),
+            RevertError::ProofValidationFailure
+        );
+
+        let data = encode("Error(string)", &[Token::String("paused".into())]);
+        assert_eq!(
+            RevertError::decode(&data),
+            RevertError::Message("paused".into())
+        );
+    }
+
+    #[test]
+    fn keeps_unknown_data() {
+        let data = Bytes::from(vec![0xde, 0xad, 0xbe, 0xef, 0x00]);
+
+        assert_eq!(RevertError::decode(&data), RevertError::Unknown(data));
+    }
+}
@@ -1,8 +1,6 @@
-use std::num::ParseIntError;
-use std::str::FromStr;
 use std::time::Duration;
 
-use anyhow::Result as AnyhowResult;
+use anyhow::{Context, Result as AnyhowResult};
 use async_trait::async_trait;
 use clap::Parser;
 use ethers::providers::Middleware;
@@ -12,15 +10,11 @@ use tracing::{info, warn};
 
 use self::openzeppelin::OzRelay;
 use super::write::{TransactionId, WriteProvider};
-use super::{ReadProvider, TxError};
+use super::{duration_from_str, ReadProvider, TxError};
 
 mod error;
 mod openzeppelin;
 
-fn duration_from_str(value: &str) -> Result<Duration, ParseIntError> {
-    Ok(Duration::from_secs(u64::from_str(value)?))
-}
-
 // TODO: Log and metrics for signer / nonces.
 #[derive(Clone, Debug, Eq, PartialEq, Parser)]
 #[group(skip)]
@@ -28,17 +22,18 @@ pub struct Options {
     #[clap(long, env, default_value = "https://api.defender.openzeppelin.com")]
     pub oz_api_url: String,
 
-    /// OpenZeppelin Defender API Key
+    /// OpenZeppelin Defender API Key. Required by the `oz` write provider.
     #[clap(long, env)]
-    pub oz_api_key: String,
+    pub oz_api_key: Option<String>,
 
-    /// OpenZeppelin Defender API Secret
+    /// OpenZeppelin Defender API Secret. Required by the `oz` write provider.
     #[clap(long, env)]
-    pub oz_api_secret: String,
+    pub oz_api_secret: Option<String>,
 
-    /// OpenZeppelin Defender API Secret
+    /// The address of the OpenZeppelin Defender relayer. Required by the `oz`
+    /// write provider.
     #[clap(long, env)]
-    pub oz_address: H160,
+    pub oz_address: Option<H160>,
 
     /// For how long OpenZeppelin should track and retry the transaction (in
     /// seconds) Default: 7 days (7 * 24 * 60 * 60 = 604800 seconds)
@@ -64,12 +59,15 @@ pub struct Provider {
 
 impl Provider {
     pub async fn new(read_provider: ReadProvider, options: &Options) -> AnyhowResult<Self> {
+        let address = options
+            .oz_address
+            .context("An OpenZeppelin relayer address is required by the oz write provider.")?;
         let relay = OzRelay::new(options).await?;
 
         Ok(Self {
             read_provider,
             inner: relay,
-            address: options.oz_address,
+            address,
         })
     }
 }
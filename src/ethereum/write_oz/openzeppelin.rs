@@ -33,7 +33,14 @@ pub struct OzRelay {
 
 impl OzRelay {
     pub async fn new(options: &Options) -> AnyhowResult<Self> {
-        let oz_api = if options.oz_api_key.is_empty() && options.oz_api_secret.is_empty() {
+        let (Some(api_key), Some(api_secret)) = (&options.oz_api_key, &options.oz_api_secret)
+        else {
+            return Err(anyhow::anyhow!(
+                "OpenZeppelin Defender API Key and Secret are required by the oz write provider."
+            ));
+        };
+
+        let oz_api = if api_key.is_empty() && api_secret.is_empty() {
             tracing::warn!(
                 "OpenZeppelin Defender API Key and Secret are empty. Connection will operate \
                  without authentication headers. Use only in development."
@@ -41,12 +48,7 @@ impl OzRelay {
 
             OzApi::without_auth(&options.oz_api_url)?
         } else {
-            OzApi::new(
-                &options.oz_api_url,
-                &options.oz_api_key,
-                &options.oz_api_secret,
-            )
-            .await?
+            OzApi::new(&options.oz_api_url, api_key, api_secret).await?
         };
 
         Ok(Self {
@@ -106,11 +108,24 @@ impl OzRelay {
         tx: T,
     ) -> Result<String, Error> {
         let tx: TypedTransaction = tx.into();
+        // The relayer only accepts EIP-1559 fees together, and otherwise picks
+        // the fees based on its configured speed
+        let (max_fee_per_gas, max_priority_fee_per_gas) = match &tx {
+            TypedTransaction::Eip1559(inner) => {
+                match (&inner.max_fee_per_gas, &inner.max_priority_fee_per_gas) {
+                    (Some(max_fee), Some(priority_fee)) => (Some(max_fee), Some(priority_fee)),
+                    _ => (None, None),
+                }
+            }
+            _ => (None, None),
+        };
         let api_tx = SendBaseTransactionRequest {
-            to:          tx.to(),
-            value:       tx.value(),
-            gas_limit:   tx.gas(),
-            data:        tx.data(),
+            to: tx.to(),
+            value: tx.value(),
+            gas_limit: tx.gas(),
+            data: tx.data(),
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
             valid_until: Some(chrono::Utc::now() + self.transaction_validity),
         };
 
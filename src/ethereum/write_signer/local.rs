@@ -0,0 +1,59 @@
+use anyhow::{anyhow, Context, Result as AnyhowResult};
+use async_trait::async_trait;
+use ethers::signers::{LocalWallet, Signer};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{Address, Bytes};
+
+use super::{Options, TransactionSigner};
+use crate::secret::SecretString;
+
+/// Signs transactions with a key held by the sequencer.
+#[derive(Debug)]
+pub struct LocalSigner {
+    wallet: LocalWallet,
+}
+
+impl LocalSigner {
+    /// Loads the key from the raw key or the keystore in the `options`.
+    pub fn new(options: &Options, chain_id: u64) -> AnyhowResult<Self> {
+        let wallet = match (&options.local_signer_key, &options.local_signer_keystore) {
+            (Some(key), None) => {
+                let key = key.expose();
+                key.strip_prefix("0x")
+                    .unwrap_or(key)
+                    .parse::<LocalWallet>()
+                    .context("Invalid local signer key")?
+            }
+            (None, Some(path)) => {
+                let password = options
+                    .local_signer_keystore_password
+                    .as_ref()
+                    .map_or("", SecretString::expose);
+                LocalWallet::decrypt_keystore(path, password)
+                    .with_context(|| format!("Failed to decrypt keystore {}", path.display()))?
+            }
+            _ => {
+                return Err(anyhow!(
+                    "Exactly one of a local signer key or keystore must be configured."
+                ))
+            }
+        };
+
+        Ok(Self {
+            wallet: wallet.with_chain_id(chain_id),
+        })
+    }
+}
+
+#[async_trait]
+impl TransactionSigner for LocalSigner {
+    fn address(&self) -> Address {
+        self.wallet.address()
+    }
+
+    async fn sign_transaction(&self, tx: &TypedTransaction) -> AnyhowResult<Bytes> {
+        let signature = self.wallet.sign_transaction(tx).await?;
+
+        Ok(tx.rlp_signed(&signature))
+    }
+}
@@ -0,0 +1,401 @@
+//! Write providers that sign transactions themselves and send them directly to
+//! the Ethereum provider.
+//!
+//! The transactions are signed either with a local key or by a remote signer,
+//! but nonces are always assigned by the sequencer, and every transaction is
+//! stored in the database before it's sent so that it can be monitored and
+//! replaced with higher fees after a restart.
+
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result as AnyhowResult;
+use async_trait::async_trait;
+use chrono::Utc;
+use clap::Parser;
+use ethers::providers::Middleware;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{Address, BlockNumber, Bytes, H256, U256, U64};
+use ethers::utils::keccak256;
+use once_cell::sync::Lazy;
+use prometheus::{register_int_counter, IntCounter};
+use tokio::sync::Mutex;
+use tokio::time::timeout;
+use tracing::{info, warn};
+
+use super::write::{TransactionId, WriteProvider};
+use super::{duration_from_str, FeePolicy, ReadProvider, TxError};
+use crate::database::types::{LocalTransaction, LocalTransactionStatus};
+use crate::database::Database;
+use crate::secret::{SecretString, SecretUrl};
+
+mod local;
+mod remote;
+
+pub use self::local::LocalSigner;
+pub use self::remote::RemoteSigner;
+
+/// How often the receipts of pending transactions are polled.
+const RECEIPT_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+static GAS_BUMPS: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "signer_gas_bumps",
+        "Number of times a transaction was replaced with higher fees."
+    )
+    .unwrap()
+});
+
+#[derive(Clone, Debug, Eq, PartialEq, Parser)]
+#[group(skip)]
+pub struct Options {
+    /// The hex encoded private key used to sign transactions. Either this or
+    /// `local_signer_keystore` is required by the local write provider.
+    #[clap(long, env)]
+    pub local_signer_key: Option<SecretString>,
+
+    /// The path to an encrypted JSON keystore holding the signing key.
+    #[clap(long, env)]
+    pub local_signer_keystore: Option<PathBuf>,
+
+    /// The password of the keystore.
+    #[clap(long, env)]
+    pub local_signer_keystore_password: Option<SecretString>,
+
+    /// The JSON-RPC endpoint of the remote signer, e.g. Web3Signer. Required
+    /// by the remote write provider.
+    #[clap(long, env)]
+    pub remote_signer_url: Option<SecretUrl>,
+
+    /// The address of the account the remote signer signs for.
+    #[clap(long, env)]
+    pub remote_signer_address: Option<Address>,
+
+    /// How long to wait for the remote signer to respond (in seconds)
+    #[clap(long, env, default_value = "30")]
+    pub remote_signer_timeout_seconds: u64,
+
+    /// How long to wait for a transaction to be mined before replacing it with
+    /// higher fees (in seconds)
+    #[clap(long, env, value_parser=duration_from_str, default_value="60")]
+    pub signer_bump_interval: Duration,
+
+    /// By how many percent the fees of a stuck transaction are increased. Most
+    /// nodes only accept replacements with at least 10% higher fees.
+    #[clap(long, env, default_value = "20")]
+    pub signer_bump_percent: u64,
+
+    #[clap(long, env, value_parser=duration_from_str, default_value="600")]
+    pub signer_mine_timeout: Duration,
+
+    #[clap(long, env)]
+    pub signer_gas_limit: Option<u64>,
+}
+
+/// Signs the transactions sent by a [`Provider`].
+#[async_trait]
+pub trait TransactionSigner: Send + Sync + fmt::Debug {
+    /// The account the transactions are sent from.
+    fn address(&self) -> Address;
+
+    /// Signs the transaction, returning the raw transaction to broadcast.
+    async fn sign_transaction(&self, tx: &TypedTransaction) -> AnyhowResult<Bytes>;
+}
+
+pub struct Provider {
+    read_provider: ReadProvider,
+    database:      Arc<Database>,
+    signer:        Arc<dyn TransactionSigner>,
+    /// The nonce of the next transaction. Held while a transaction is sent so
+    /// that nonces are assigned in order.
+    next_nonce:    Mutex<U256>,
+    fee_policy:    FeePolicy,
+    bump_interval: Duration,
+    bump_percent:  u64,
+    mine_timeout:  Duration,
+    gas_limit:     Option<u64>,
+}
+
+impl fmt::Debug for Provider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Provider")
+            .field("signer", &self.signer)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Provider {
+    pub async fn new(
+        read_provider: ReadProvider,
+        database: Arc<Database>,
+        signer: Arc<dyn TransactionSigner>,
+        fee_policy: FeePolicy,
+        options: &Options,
+    ) -> AnyhowResult<Self> {
+        let address = signer.address();
+
+        // Transactions that are stored but not yet in the mempool of the provider
+        // still hold their nonce
+        let chain_nonce = read_provider
+            .get_transaction_count(address, Some(BlockNumber::Pending.into()))
+            .await?;
+        let stored_nonce = database.get_next_local_nonce(address).await?;
+        let next_nonce = stored_nonce.map_or(chain_nonce, |nonce| chain_nonce.max(nonce.into()));
+
+        info!(?address, %next_nonce, "Signer initialized");
+
+        Ok(Self {
+            read_provider,
+            database,
+            signer,
+            next_nonce: Mutex::new(next_nonce),
+            fee_policy,
+            bump_interval: options.signer_bump_interval,
+            bump_percent: options.signer_bump_percent,
+            mine_timeout: options.signer_mine_timeout,
+            gas_limit: options.signer_gas_limit,
+        })
+    }
+
+    /// Signs the transaction, returning its hash and the raw transaction to
+    /// broadcast.
+    async fn sign(&self, tx: &TypedTransaction) -> Result<(H256, Bytes), TxError> {
+        let raw = self
+            .signer
+            .sign_transaction(tx)
+            .await
+            .map_err(|error| TxError::Fill(error.into()))?;
+        let hash = H256::from(keccak256(&raw));
+
+        Ok((hash, raw))
+    }
+
+    fn bump(&self, value: U256) -> U256 {
+        value * (100 + self.bump_percent) / 100
+    }
+
+    /// Replaces a stuck transaction with one that pays higher fees, and at
+    /// least the current market rate, up to the maximum fee of the fee
+    /// policy.
+    async fn bump_fees(&self, local_tx: &LocalTransaction) -> Result<(), TxError> {
+        let mut tx = local_tx.tx.clone();
+
+        if let TypedTransaction::Eip1559(inner) = &mut tx {
+            let (max_fee, priority_fee) = self
+                .read_provider
+                .estimate_eip1559_fees(None)
+                .await
+                .map_err(|error| TxError::Fill(Box::new(error)))?;
+
+            inner.max_fee_per_gas = Some(
+                self.fee_policy.cap(
+                    inner
+                        .max_fee_per_gas
+                        .map_or(max_fee, |fee| self.bump(fee).max(max_fee)),
+                ),
+            );
+            inner.max_priority_fee_per_gas = Some(
+                self.fee_policy.cap(
+                    inner
+                        .max_priority_fee_per_gas
+                        .map_or(priority_fee, |fee| self.bump(fee).max(priority_fee)),
+                ),
+            );
+        } else {
+            let gas_price = self
+                .read_provider
+                .get_gas_price()
+                .await
+                .map_err(|error| TxError::Fill(Box::new(error)))?;
+
+            tx.set_gas_price(
+                self.fee_policy.cap(
+                    tx.gas_price()
+                        .map_or(gas_price, |price| self.bump(price).max(gas_price)),
+                ),
+            );
+        }
+
+        if tx == local_tx.tx {
+            // Already paying the maximum fee
+            return Ok(());
+        }
+
+        let (tx_hash, raw) = self.sign(&tx).await?;
+
+        self.database
+            .update_local_transaction(&local_tx.id, &tx, tx_hash)
+            .await
+            .map_err(|error| TxError::Send(Box::new(error)))?;
+
+        warn!(
+            id = local_tx.id,
+            ?tx_hash,
+            "Replacing stuck transaction with higher fees"
+        );
+        GAS_BUMPS.inc();
+
+        // If an earlier version was mined in the meantime the replacement is
+        // rejected, and the receipt of the mined version is found instead
+        if let Err(error) = self.read_provider.send_raw_transaction(raw).await {
+            warn!(
+                id = local_tx.id,
+                ?error,
+                "Failed to send replacement transaction"
+            );
+        }
+
+        Ok(())
+    }
+
+    async fn mine_transaction_unchecked(&self, id: &str) -> Result<bool, TxError> {
+        loop {
+            let local_tx = self
+                .database
+                .get_local_transaction(id)
+                .await
+                .map_err(|error| TxError::Fetch(Box::new(error)))?
+                .ok_or_else(|| {
+                    TxError::Fetch(From::from(format!("Unknown transaction id {id}")))
+                })?;
+
+            match local_tx.status {
+                LocalTransactionStatus::Mined => return Ok(true),
+                LocalTransactionStatus::Failed => return Ok(false),
+                LocalTransactionStatus::Pending => {}
+            }
+
+            for tx_hash in &local_tx.tx_hashes {
+                let receipt = self
+                    .read_provider
+                    .get_transaction_receipt(*tx_hash)
+                    .await
+                    .map_err(|error| TxError::Fetch(Box::new(error)))?;
+
+                let Some(receipt) = receipt else {
+                    continue;
+                };
+
+                let succeeded = receipt.status == Some(U64::from(1u64));
+                let status = if succeeded {
+                    LocalTransactionStatus::Mined
+                } else {
+                    warn!(?receipt, "Transaction failed");
+                    LocalTransactionStatus::Failed
+                };
+
+                self.database
+                    .set_local_transaction_status(id, status)
+                    .await
+                    .map_err(|error| TxError::Fetch(Box::new(error)))?;
+
+                return Ok(succeeded);
+            }
+
+            let pending_for = (Utc::now() - local_tx.sent_at).to_std().unwrap_or_default();
+            if pending_for >= self.bump_interval {
+                self.bump_fees(&local_tx).await?;
+            }
+
+            tokio::time::sleep(RECEIPT_POLL_INTERVAL).await;
+        }
+    }
+}
+
+#[async_trait]
+impl WriteProvider for Provider {
+    async fn send_transaction(
+        &self,
+        tx: TypedTransaction,
+        only_once: bool,
+    ) -> Result<TransactionId, TxError> {
+        let address = self.signer.address();
+        let data_hash = H256::from(keccak256(tx.data().map_or(&[][..], |data| data.as_ref())));
+
+        if only_once {
+            let existing = self
+                .database
+                .get_pending_local_transaction_by_data(address, data_hash)
+                .await
+                .map_err(|error| TxError::Send(Box::new(error)))?;
+
+            if let Some(existing) = existing {
+                info!(
+                    only_once,
+                    id = existing.id,
+                    "Found previously sent transaction"
+                );
+
+                return Ok(TransactionId(existing.id));
+            }
+        }
+
+        let mut next_nonce = self.next_nonce.lock().await;
+        let nonce = *next_nonce;
+
+        let mut tx = tx;
+        tx.set_from(address);
+        tx.set_nonce(nonce);
+        tx.set_chain_id(self.read_provider.chain_id.as_u64());
+        if let Some(gas_limit) = self.gas_limit {
+            tx.set_gas(gas_limit);
+        }
+
+        self.read_provider
+            .fill_transaction(&mut tx, None)
+            .await
+            .map_err(|error| TxError::Fill(Box::new(error)))?;
+        // Estimated gas prices of legacy transactions are capped as well
+        self.fee_policy.apply(&mut tx);
+
+        let (tx_hash, raw) = self.sign(&tx).await?;
+        let id = format!("{address:?}-{nonce}");
+
+        // The transaction is stored first so that it's never lost, even if the
+        // sequencer stops right after sending it
+        self.database
+            .insert_local_transaction(&id, address, nonce.as_u64(), data_hash, &tx, tx_hash)
+            .await
+            .map_err(|error| TxError::Send(Box::new(error)))?;
+
+        info!(id, ?tx_hash, %nonce, "Sending transaction.");
+
+        if let Err(error) = self.read_provider.send_raw_transaction(raw).await {
+            // The nonce is reused for the next transaction
+            if let Err(delete_error) = self.database.delete_local_transaction(&id).await {
+                warn!(id, ?delete_error, "Failed to delete unsent transaction");
+            }
+
+            return Err(TxError::Send(Box::new(error)));
+        }
+
+        *next_nonce = nonce + 1;
+
+        Ok(TransactionId(id))
+    }
+
+    async fn fetch_pending_transactions(&self) -> Result<Vec<TransactionId>, TxError> {
+        let pending = self
+            .database
+            .get_pending_local_transactions(self.signer.address())
+            .await
+            .map_err(|error| TxError::Fetch(Box::new(error)))?;
+
+        Ok(pending.into_iter().map(|tx| TransactionId(tx.id)).collect())
+    }
+
+    async fn mine_transaction(&self, tx: TransactionId) -> Result<bool, TxError> {
+        timeout(
+            self.mine_timeout,
+            self.mine_transaction_unchecked(tx.as_ref()),
+        )
+        .await
+        .map_err(|_| TxError::ConfirmationTimeout)?
+    }
+
+    fn address(&self) -> Address {
+        self.signer.address()
+    }
+}
@@ -0,0 +1,60 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result as AnyhowResult};
+use async_trait::async_trait;
+use ethers::providers::{Http, JsonRpcClient};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{Address, Bytes};
+use tracing::info;
+use url::Url;
+
+use super::{Options, TransactionSigner};
+
+/// Delegates signing to an external signer, such as Web3Signer or a KMS proxy,
+/// through the `eth_signTransaction` JSON-RPC method.
+///
+/// The signer only signs: nonces and fees are filled in by the sequencer
+/// before the transaction is sent for signing.
+#[derive(Debug)]
+pub struct RemoteSigner {
+    client:  Http,
+    address: Address,
+}
+
+impl RemoteSigner {
+    pub fn new(options: &Options) -> AnyhowResult<Self> {
+        let url = options
+            .remote_signer_url
+            .as_ref()
+            .context("A remote signer URL is required by the remote write provider.")?;
+        let address = options
+            .remote_signer_address
+            .context("A remote signer address is required by the remote write provider.")?;
+
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(options.remote_signer_timeout_seconds))
+            .build()?;
+        let client = Http::new_with_client(Url::parse(url.expose())?, client);
+
+        info!(%url, ?address, "Using remote signer");
+
+        Ok(Self { client, address })
+    }
+}
+
+#[async_trait]
+impl TransactionSigner for RemoteSigner {
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    async fn sign_transaction(&self, tx: &TypedTransaction) -> AnyhowResult<Bytes> {
+        let signed = self
+            .client
+            .request("eth_signTransaction", [tx])
+            .await
+            .context("Remote signer failed to sign the transaction")?;
+
+        Ok(signed)
+    }
+}
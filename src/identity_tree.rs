@@ -1,4 +1,6 @@
 use std::cmp::min;
+use std::collections::VecDeque;
+use std::mem;
 use std::str::FromStr;
 use std::sync::{Arc, Mutex, MutexGuard};
 
@@ -14,6 +16,10 @@ use tracing::{info, warn};
 pub type PoseidonTree<Version> = LazyMerkleTree<PoseidonHash, Version>;
 pub type Hash = <PoseidonHash as Hasher>::Hash;
 
+/// The number of updates applied to the canonical tree version that are
+/// remembered, so that they can be rewound after a chain reorg.
+const REWIND_HISTORY_SIZE: usize = 10_000;
+
 #[derive(Clone, Eq, PartialEq, Hash, Debug)]
 pub struct TreeUpdate {
     pub leaf_index: usize,
@@ -120,6 +126,17 @@ pub struct InclusionProof {
 pub struct CanonicalTreeMetadata {
     flatten_threshold:        usize,
     count_since_last_flatten: usize,
+    /// The most recent updates pulled from the next version, oldest first.
+    history:                  VecDeque<RewindEntry>,
+}
+
+/// An update applied to the canonical tree version, along with the state it
+/// replaced.
+struct RewindEntry {
+    update:             TreeUpdate,
+    previous_element:   Hash,
+    previous_next_leaf: usize,
+    previous_root:      Hash,
 }
 
 /// Additional data held by any derived tree version. Includes the list of
@@ -276,6 +293,17 @@ impl BasicTreeOps for TreeVersionData<lazy_merkle_tree::Canonical> {
     fn apply_diffs(&mut self, diffs: Vec<AppliedTreeUpdate>) {
         for applied_update in &diffs {
             let update = &applied_update.update;
+
+            self.metadata.history.push_back(RewindEntry {
+                update:             update.clone(),
+                previous_element:   self.tree.get_leaf(update.leaf_index),
+                previous_next_leaf: self.next_leaf,
+                previous_root:      self.tree.root(),
+            });
+            if self.metadata.history.len() > REWIND_HISTORY_SIZE {
+                self.metadata.history.pop_front();
+            }
+
             self.update(update.leaf_index, update.element);
         }
     }
@@ -301,6 +329,105 @@ impl BasicTreeOps for TreeVersionData<lazy_merkle_tree::Canonical> {
     }
 }
 
+impl TreeVersionData<lazy_merkle_tree::Canonical> {
+    /// Rewinds this version and the next one to `root`. The rewound updates are
+    /// handed back to the version after the next one, so that they can be
+    /// applied again.
+    ///
+    /// Returns the number of rewound updates, or `None` if `root` is neither a
+    /// root of the next version nor one of the remembered roots of this one.
+    fn rewind_to(&mut self, root: Hash) -> Option<usize> {
+        let next = self.next.clone()?;
+        let mut next = next.get_data();
+
+        let mut rewound = if let Some(index) = next
+            .metadata
+            .diff
+            .iter()
+            .position(|update| update.result.root() == root)
+        {
+            next.metadata.diff.split_off(index + 1)
+        } else if self.tree.root() == root {
+            mem::take(&mut next.metadata.diff)
+        } else {
+            let mut rewound = self.rewind_history_to(root)?;
+            rewound.append(&mut next.metadata.diff);
+            rewound
+        };
+
+        let rewound_count = rewound.len();
+
+        next.next_leaf = next
+            .metadata
+            .diff
+            .iter()
+            .rev()
+            .find(|update| update.update.element != Hash::ZERO)
+            .map_or(self.next_leaf, |update| update.update.leaf_index + 1);
+
+        if let Some(after_next) = next.next.as_ref() {
+            let mut after_next = after_next.get_data();
+            rewound.append(&mut after_next.metadata.diff);
+            after_next.metadata.diff = rewound;
+        }
+
+        next.rebuild_on(self.tree.derived());
+
+        Some(rewound_count)
+    }
+
+    /// Rewinds only this version to `root`. The rewound updates are handed
+    /// back to the next version, which is left as it is.
+    ///
+    /// Returns the number of rewound updates, or `None` if `root` is neither
+    /// the root of this version nor one of its remembered roots.
+    fn rewind_self_to(&mut self, root: Hash) -> Option<usize> {
+        if self.tree.root() == root {
+            return Some(0);
+        }
+
+        let next = self.next.clone()?;
+        let mut rewound = self.rewind_history_to(root)?;
+        let rewound_count = rewound.len();
+
+        let mut next = next.get_data();
+        rewound.append(&mut next.metadata.diff);
+        next.metadata.diff = rewound;
+        next.rebuild_on(self.tree.derived());
+
+        Some(rewound_count)
+    }
+
+    /// Undoes the remembered updates of this version after `root`, and returns
+    /// them in the order they were applied.
+    fn rewind_history_to(&mut self, root: Hash) -> Option<Vec<AppliedTreeUpdate>> {
+        let index = self
+            .metadata
+            .history
+            .iter()
+            .rposition(|entry| entry.previous_root == root)?;
+        let entries: Vec<_> = self.metadata.history.drain(index..).collect();
+
+        for entry in entries.iter().rev() {
+            take_mut::take(&mut self.tree, |tree| {
+                tree.update_with_mutation(entry.update.leaf_index, &entry.previous_element)
+            });
+            self.next_leaf = entry.previous_next_leaf;
+        }
+
+        // The results are recomputed when the next version is rebuilt
+        let rewound = entries
+            .into_iter()
+            .map(|entry| AppliedTreeUpdate {
+                update: entry.update,
+                result: self.tree.derived(),
+            })
+            .collect();
+
+        Some(rewound)
+    }
+}
+
 impl TreeVersionData<lazy_merkle_tree::Derived> {
     fn rebuild_on(&mut self, mut tree: PoseidonTree<lazy_merkle_tree::Derived>) {
         for update in &mut self.metadata.diff {
@@ -475,6 +602,29 @@ impl<V: Version> TreeVersion<V> {
     }
 }
 
+impl TreeVersion<Canonical> {
+    /// Rewinds this version and the processed version after it to `root`,
+    /// e.g. after a chain reorg. The rewound updates are handed back to the
+    /// batching version.
+    ///
+    /// Returns the number of rewound updates, or `None` if `root` is unknown.
+    /// Only the most recent updates of this version can be rewound.
+    #[must_use]
+    pub fn rewind_to(&self, root: Hash) -> Option<usize> {
+        self.get_data().rewind_to(root)
+    }
+
+    /// Rewinds only this version to `root`, e.g. after a chain reorg on a
+    /// secondary chain. The rewound updates are handed back to the processed
+    /// version, so that they can be applied again.
+    ///
+    /// Returns the number of rewound updates, or `None` if `root` is unknown.
+    #[must_use]
+    pub fn rewind_mined_to(&self, root: Hash) -> Option<usize> {
+        self.get_data().rewind_self_to(root)
+    }
+}
+
 impl TreeVersion<Latest> {
     /// Appends many identities to the tree, returns a list with the root, proof
     /// of inclusion and leaf index
@@ -657,6 +807,7 @@ impl CanonicalTreeBuilder {
         let metadata = CanonicalTreeMetadata {
             flatten_threshold:        flattening_threshold,
             count_since_last_flatten: 0,
+            history:                  VecDeque::new(),
         };
         let mut builder = Self(TreeVersionData {
             tree,
@@ -745,7 +896,7 @@ impl<P: Version> DerivedTreeBuilder<P> {
 #[cfg(test)]
 mod tests {
 
-    use super::{CanonicalTreeBuilder, Hash, TreeWithNextVersion};
+    use super::{CanonicalTreeBuilder, Hash, TreeVersionReadOps, TreeWithNextVersion};
 
     #[test]
     fn test_peek_next_updates() {
@@ -785,4 +936,56 @@ mod tests {
 
         assert_eq!(next_updates.len(), 3);
     }
+
+    #[test]
+    fn test_rewind_to() {
+        let (mined_tree, processed_builder) =
+            CanonicalTreeBuilder::new(10, 10, 0, Hash::ZERO, &[]).seal();
+        let (processed_tree, batching_builder) = processed_builder.seal_and_continue();
+        let (batching_tree, latest_builder) = batching_builder.seal_and_continue();
+        let latest_tree = latest_builder.seal();
+
+        let initial_root = mined_tree.get_root();
+        let updates =
+            latest_tree.append_many(&[Hash::from(1), Hash::from(2), Hash::from(3), Hash::from(4)]);
+        let (first_root, second_root, last_root) = (updates[0].0, updates[1].0, updates[3].0);
+
+        batching_tree.apply_updates_up_to(last_root);
+        processed_tree.apply_updates_up_to(last_root);
+        mined_tree.apply_updates_up_to(second_root);
+
+        // Rewinding within the processed version leaves the mined version as it is
+        assert_eq!(mined_tree.rewind_to(updates[2].0), Some(1));
+        assert_eq!(mined_tree.get_root(), second_root);
+        assert_eq!(processed_tree.get_root(), updates[2].0);
+        assert_eq!(processed_tree.next_leaf(), 3);
+
+        // Rewinding the mined version rewinds the processed version with it
+        assert_eq!(mined_tree.rewind_to(first_root), Some(2));
+        assert_eq!(mined_tree.get_root(), first_root);
+        assert_eq!(mined_tree.next_leaf(), 1);
+        assert_eq!(processed_tree.get_root(), first_root);
+        assert_eq!(batching_tree.get_root(), last_root);
+        assert_eq!(latest_tree.get_root(), last_root);
+
+        // The rewound updates can be applied again
+        assert_eq!(batching_tree.peek_next_updates(10).len(), 0);
+        assert_eq!(processed_tree.peek_next_updates(10).len(), 3);
+        assert_eq!(processed_tree.apply_updates_up_to(last_root), 3);
+        assert_eq!(mined_tree.apply_updates_up_to(last_root), 3);
+        assert_eq!(mined_tree.get_root(), last_root);
+
+        // Rewinding only the mined version leaves the processed version as it is
+        assert_eq!(mined_tree.rewind_mined_to(second_root), Some(2));
+        assert_eq!(mined_tree.get_root(), second_root);
+        assert_eq!(mined_tree.next_leaf(), 2);
+        assert_eq!(processed_tree.get_root(), last_root);
+        assert_eq!(mined_tree.apply_updates_up_to(last_root), 2);
+        assert_eq!(mined_tree.rewind_mined_to(last_root), Some(0));
+
+        assert_eq!(mined_tree.rewind_to(initial_root), Some(4));
+        assert_eq!(mined_tree.get_root(), initial_root);
+        assert_eq!(mined_tree.rewind_to(Hash::from(42)), None);
+        assert_eq!(mined_tree.rewind_mined_to(Hash::from(42)), None);
+    }
 }
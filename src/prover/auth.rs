@@ -0,0 +1,228 @@
+//! Authentication of the requests sent to the provers.
+//!
+//! Provers that aren't on a private network can require every request to be
+//! authenticated, either with a bearer token, by signing the request with a
+//! shared key, or with a client TLS certificate.
+
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+use hmac::{Hmac, Mac};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION};
+use reqwest::{Certificate, ClientBuilder, Identity, Request};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::secret::SecretString;
+
+/// The header carrying the time at which a signed request was made, in
+/// seconds since the UNIX epoch.
+pub const SIGNATURE_TIMESTAMP_HEADER: &str = "x-signature-timestamp";
+
+fn default_signature_header() -> String {
+    "x-signature".to_string()
+}
+
+/// How requests to a prover are authenticated.
+///
+/// Secrets are redacted when the configuration is logged or serialized.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ProverAuth {
+    /// The token is sent in the `Authorization` header of every request.
+    #[serde(rename_all = "camelCase")]
+    Bearer { token: SecretString },
+    /// Every request is signed with HMAC-SHA256 using the shared `key`.
+    ///
+    /// The signature covers the timestamp, the method, the path and the body
+    /// of the request, separated by newlines. It's sent hex-encoded in the
+    /// `header`, next to the timestamp in [`SIGNATURE_TIMESTAMP_HEADER`].
+    #[serde(rename_all = "camelCase")]
+    Hmac {
+        key:    SecretString,
+        #[serde(default = "default_signature_header")]
+        header: String,
+    },
+    /// The client authenticates with a TLS certificate. Both files are in the
+    /// PEM format, and the key must be PKCS #8 encoded.
+    #[serde(rename_all = "camelCase")]
+    ClientTls {
+        certificate_path:    PathBuf,
+        key_path:            PathBuf,
+        /// An additional CA certificate to trust, for provers that use a
+        /// private CA.
+        #[serde(default)]
+        ca_certificate_path: Option<PathBuf>,
+    },
+}
+
+impl ProverAuth {
+    /// Configures the `builder` for the authentication methods that apply to
+    /// every request.
+    pub fn configure_client(&self, builder: ClientBuilder) -> anyhow::Result<ClientBuilder> {
+        match self {
+            Self::Bearer { token } => {
+                let mut value = HeaderValue::from_str(&format!("Bearer {}", token.expose()))
+                    .context("Invalid prover bearer token")?;
+                value.set_sensitive(true);
+
+                let mut headers = HeaderMap::new();
+                headers.insert(AUTHORIZATION, value);
+
+                Ok(builder.default_headers(headers))
+            }
+            Self::Hmac { header, .. } => {
+                HeaderName::from_bytes(header.as_bytes())
+                    .with_context(|| format!("Invalid prover signature header {header}"))?;
+
+                Ok(builder)
+            }
+            Self::ClientTls {
+                certificate_path,
+                key_path,
+                ca_certificate_path,
+            } => {
+                let certificate = std::fs::read(certificate_path).with_context(|| {
+                    format!(
+                        "Failed to read client certificate at {}",
+                        certificate_path.display()
+                    )
+                })?;
+                let key = std::fs::read(key_path).with_context(|| {
+                    format!("Failed to read client key at {}", key_path.display())
+                })?;
+                let identity = Identity::from_pkcs8_pem(&certificate, &key)
+                    .context("Invalid client certificate or key")?;
+
+                let mut builder = builder.identity(identity);
+
+                if let Some(path) = ca_certificate_path {
+                    let ca_certificate = std::fs::read(path).with_context(|| {
+                        format!("Failed to read CA certificate at {}", path.display())
+                    })?;
+                    builder = builder.add_root_certificate(Certificate::from_pem(&ca_certificate)?);
+                }
+
+                Ok(builder)
+            }
+        }
+    }
+
+    /// Adds the authentication that depends on the contents of the `request`.
+    pub fn authenticate(&self, request: &mut Request) -> anyhow::Result<()> {
+        let Self::Hmac { key, header } = self else {
+            return Ok(());
+        };
+
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let body = request
+            .body()
+            .and_then(reqwest::Body::as_bytes)
+            .unwrap_or_default();
+        let signature = sign(
+            key.expose(),
+            timestamp,
+            request.method().as_str(),
+            request.url().path(),
+            body,
+        )?;
+
+        let headers = request.headers_mut();
+        headers.insert(SIGNATURE_TIMESTAMP_HEADER, HeaderValue::from(timestamp));
+        headers.insert(
+            HeaderName::from_bytes(header.as_bytes())?,
+            HeaderValue::from_str(&signature)?,
+        );
+
+        Ok(())
+    }
+
+    /// Serializes the configuration including its secrets, so that it can be
+    /// persisted.
+    #[must_use]
+    pub fn to_exposed_json(&self) -> String {
+        let value = match self {
+            Self::Bearer { token } => serde_json::json!({
+                "type": "bearer",
+                "token": token.expose(),
+            }),
+            Self::Hmac { key, header } => serde_json::json!({
+                "type": "hmac",
+                "key": key.expose(),
+                "header": header,
+            }),
+            Self::ClientTls { .. } => serde_json::to_value(self)
+                .expect("client TLS configuration contains no secrets and always serializes"),
+        };
+
+        value.to_string()
+    }
+}
+
+/// Computes the hex-encoded HMAC-SHA256 signature of a request.
+fn sign(
+    key: &str,
+    timestamp: u64,
+    method: &str,
+    path: &str,
+    body: &[u8],
+) -> anyhow::Result<String> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key.as_bytes())?;
+    mac.update(format!("{timestamp}\n{method}\n{path}\n").as_bytes());
+    mac.update(body);
+
+    Ok(hex::encode(mac.finalize().into_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secrets_are_redacted() {
+        let auth: ProverAuth =
+            serde_json::from_str(r#"{"type": "hmac", "key": "shared-key"}"#).unwrap();
+
+        assert_eq!(auth, ProverAuth::Hmac {
+            key:    SecretString::new("shared-key".into()),
+            header: "x-signature".into(),
+        });
+        assert!(!format!("{auth:?}").contains("shared-key"));
+        assert!(!serde_json::to_string(&auth).unwrap().contains("shared-key"));
+
+        let stored: ProverAuth = serde_json::from_str(&auth.to_exposed_json()).unwrap();
+        assert_eq!(stored, auth);
+    }
+
+    #[test]
+    fn signs_requests() {
+        let mut request = reqwest::Client::new()
+            .post("http://localhost:3001/prove")
+            .body(r#"{"batchSize":3}"#)
+            .build()
+            .unwrap();
+
+        let auth = ProverAuth::Hmac {
+            key:    SecretString::new("shared-key".into()),
+            header: "x-signature".into(),
+        };
+        auth.authenticate(&mut request).unwrap();
+
+        let timestamp: u64 = request.headers()[SIGNATURE_TIMESTAMP_HEADER]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        let expected = sign(
+            "shared-key",
+            timestamp,
+            "POST",
+            "/prove",
+            br#"{"batchSize":3}"#,
+        )
+        .unwrap();
+
+        assert_eq!(request.headers()["x-signature"], expected.as_str());
+    }
+}
@@ -2,7 +2,7 @@ use std::collections::BTreeMap;
 
 use tokio::sync::{RwLock, RwLockReadGuard};
 
-use crate::prover::{Prover, ProverConfiguration, ProverType, Provers};
+use crate::prover::{ProofVerifiers, Prover, ProverConfiguration, ProverPool, ProverType, Provers};
 
 /// The type of a map containing a mapping from a usize to a locked item.
 type SharedProverMap<P> = RwLock<ProverMap<P>>;
@@ -10,7 +10,7 @@ type SharedProverMap<P> = RwLock<ProverMap<P>>;
 /// A prover that can have read-only operations performed on it.
 pub type ReadOnlyProver<'a, P> = RwLockReadGuard<'a, P>;
 
-/// A map that contains a prover for each batch size.
+/// A map that contains a prover pool for each batch size.
 ///
 /// Provides utility methods for getting the appropriate provers
 ///
@@ -36,11 +36,33 @@ impl<P> ProverMap<P> {
         None
     }
 
+    /// Get the smallest prover that can handle the given batch size and
+    /// satisfies the `predicate`.
+    pub fn get_matching(&self, batch_size: usize, predicate: impl Fn(&P) -> bool) -> Option<&P> {
+        self.fitting(batch_size).find(|prover| predicate(prover))
+    }
+
+    /// Iterates over the provers that can handle the given batch size, from
+    /// the smallest to the largest.
+    pub fn fitting(&self, batch_size: usize) -> impl Iterator<Item = &P> {
+        self.map.range(batch_size..).map(|(_, prover)| prover)
+    }
+
+    /// Iterates over all the provers in the map.
+    pub fn values(&self) -> impl Iterator<Item = &P> {
+        self.map.values()
+    }
+
     /// Registers the provided `prover` for the given `batch_size` in the map.
     pub fn add(&mut self, batch_size: usize, prover: P) {
         self.map.insert(batch_size, prover);
     }
 
+    /// Get the prover registered for exactly the given batch size.
+    pub fn get_exact_mut(&mut self, batch_size: usize) -> Option<&mut P> {
+        self.map.get_mut(&batch_size)
+    }
+
     /// Removes the prover for the provided `batch_size` from the prover map.
     pub fn remove(&mut self, batch_size: usize) -> Option<P> {
         self.map.remove(&batch_size)
@@ -59,16 +81,11 @@ impl<P> ProverMap<P> {
     }
 }
 
-impl ProverMap<Prover> {
+impl ProverMap<ProverPool> {
     pub fn as_configuration_vec(&self) -> Vec<ProverConfiguration> {
         self.map
-            .iter()
-            .map(|(k, v)| ProverConfiguration {
-                url:         v.url(),
-                timeout_s:   v.timeout_s(),
-                batch_size:  *k,
-                prover_type: v.prover_type(),
-            })
+            .values()
+            .flat_map(ProverPool::as_configuration_vec)
             .collect()
     }
 }
@@ -80,28 +97,37 @@ impl<P> From<BTreeMap<usize, P>> for ProverMap<P> {
 }
 
 /// A map of provers for batch insertion operations.
-pub type InsertionProverMap = SharedProverMap<Prover>;
+pub type InsertionProverMap = SharedProverMap<ProverPool>;
 /// A map of provers for batch deletion operations.
-pub type DeletionProverMap = SharedProverMap<Prover>;
+pub type DeletionProverMap = SharedProverMap<ProverPool>;
 
 /// The type of provers that can only be read from for insertion operations.
-pub type ReadOnlyInsertionProver<'a> = ReadOnlyProver<'a, Prover>;
+pub type ReadOnlyInsertionProver<'a> = ReadOnlyProver<'a, ProverPool>;
 
 /// Builds an insertion prover map from the provided configuration.
+///
+/// Pools with a verifying key in `verifiers` check the proofs they return.
 pub fn initialize_prover_maps(
     db_provers: Provers,
+    verifiers: &ProofVerifiers,
 ) -> anyhow::Result<(InsertionProverMap, DeletionProverMap)> {
     let mut insertion_map = BTreeMap::new();
     let mut deletion_map = BTreeMap::new();
 
     for prover in db_provers {
-        match prover.prover_type {
-            ProverType::Insertion => {
-                insertion_map.insert(prover.batch_size, Prover::from_prover_conf(&prover)?);
-            }
-
-            ProverType::Deletion => {
-                deletion_map.insert(prover.batch_size, Prover::from_prover_conf(&prover)?);
+        let map = match prover.prover_type {
+            ProverType::Insertion => &mut insertion_map,
+            ProverType::Deletion => &mut deletion_map,
+        };
+
+        // Provers with the same batch size are replicas of each other
+        let replica = Prover::from_prover_conf(&prover)?;
+        match map.get_mut(&prover.batch_size) {
+            Some(pool) => pool.add_replica(replica)?,
+            None => {
+                let mut pool = ProverPool::new(replica);
+                pool.set_verifier(verifiers.get(prover.prover_type, prover.batch_size));
+                map.insert(prover.batch_size, pool);
             }
         }
     }
@@ -132,5 +158,10 @@ mod tests {
         assert_eq!(prover_map.get(4), Some(&5));
         assert_eq!(prover_map.get(7), Some(&7));
         assert!(prover_map.get(8).is_none());
+
+        assert_eq!(prover_map.get_matching(1, |size| *size != 3), Some(&5));
+        assert_eq!(prover_map.get_matching(4, |size| *size != 5), Some(&7));
+        assert!(prover_map.get_matching(6, |size| *size != 7).is_none());
+        assert_eq!(prover_map.fitting(4).count(), 2);
     }
 }
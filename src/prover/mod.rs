@@ -7,9 +7,12 @@
 //! APIs are designed to be imported for use qualified (e.g.
 //! `batch_insertion::Prover`, `batch_insertion::Identity` and so on).
 
+pub mod auth;
 pub mod identity;
 pub mod map;
+pub mod pool;
 pub mod proof;
+pub mod verifier;
 
 use std::collections::HashSet;
 use std::fmt::{Display, Formatter};
@@ -17,15 +20,22 @@ use std::hash::{Hash, Hasher};
 use std::mem::size_of;
 use std::time::Duration;
 
+pub use auth::ProverAuth;
 use clap::Parser;
 use ethers::types::U256;
 use ethers::utils::keccak256;
 pub use map::{InsertionProverMap, ProverMap, ReadOnlyProver};
 use once_cell::sync::Lazy;
-use prometheus::{exponential_buckets, register_histogram, Histogram};
+pub use pool::ProverPool;
+use prometheus::{
+    exponential_buckets, register_histogram_vec, register_int_counter_vec, HistogramVec,
+    IntCounterVec,
+};
 pub use proof::Proof;
 use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
 use url::Url;
+pub use verifier::ProofVerifiers;
 
 use crate::prover::identity::Identity;
 use crate::serde_utils::JsonStrWrapper;
@@ -34,36 +44,79 @@ use crate::utils::index_packing::pack_indices;
 /// The endpoint used for proving operations.
 const MTB_PROVE_ENDPOINT: &str = "prove";
 
-static TOTAL_PROVING_TIME: Lazy<Histogram> = Lazy::new(|| {
-    register_histogram!(
+/// The endpoint used to query the circuit parameters of a prover.
+const MTB_INFO_ENDPOINT: &str = "info";
+
+/// The endpoint used to submit proving jobs to provers that use the
+/// asynchronous protocol.
+const MTB_JOBS_ENDPOINT: &str = "jobs";
+
+/// How often the status of an asynchronous proving job is polled.
+const JOB_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long requests that only probe a prover, as opposed to generating a
+/// proof, may take before the prover is considered unreachable.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The labels of the metrics that are tracked per prover.
+const PROVER_METRIC_LABELS: [&str; 3] = ["url", "batch_size", "prover_type"];
+
+static TOTAL_PROVING_TIME: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
         "total_proving_time",
         "The time to generate a proof in seconds. Includes preparing the data for the prover",
+        &PROVER_METRIC_LABELS,
         exponential_buckets(0.1, 1.5, 25).unwrap()
     )
     .unwrap()
 });
 
-static PROVER_PROVING_TIME: Lazy<Histogram> = Lazy::new(|| {
-    register_histogram!(
+static PROVER_PROVING_TIME: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
         "prover_proving_time",
         "Only the time between sending a request and receiving the proof",
+        &PROVER_METRIC_LABELS,
         exponential_buckets(0.1, 1.5, 25).unwrap()
     )
     .unwrap()
 });
 
+static PROVER_REQUESTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "prover_requests",
+        "Number of proof requests sent to a prover, by outcome. The outcome is `success`, \
+         `rejected` if the prover refused the inputs, or `error` if the request failed.",
+        &["url", "batch_size", "prover_type", "outcome"]
+    )
+    .unwrap()
+});
+
 #[derive(Clone, Debug, PartialEq, Eq, Parser)]
 #[group(skip)]
 pub struct Options {
     /// The options for configuring the batch insertion prover service.
     ///
     /// This should be a JSON array containing objects of the following format `{"url": "http://localhost:3001","batch_size": 3,"timeout_s": 30,"prover_type", "insertion"}`
+    ///
+    /// Provers use the synchronous protocol unless `"protocol": "async"` is
+    /// set. Requests are authenticated if an `"auth"` object is set, for
+    /// example `{"type": "bearer", "token": "..."}`.
     #[clap(
         long,
         env,
         default_value = r#"[{"url": "http://localhost:3001","batch_size": 3,"timeout_s": 30,"prover_type": "insertion"}]"# //TODO: update this and test
     )]
     pub prover_urls: JsonStrWrapper<Vec<ProverConfiguration>>,
+
+    /// The verifying keys used to check proofs locally before they are
+    /// submitted on chain. Proofs from provers without a verifying key are not
+    /// checked.
+    ///
+    /// This should be a JSON array containing objects of the following format
+    /// `{"batch_size": 3,"prover_type": "insertion","path":
+    /// "keys/insertion_3.json"}`
+    #[clap(long, env, default_value = "[]")]
+    pub verifying_keys: JsonStrWrapper<Vec<verifier::VerifyingKeyConfiguration>>,
 }
 
 /// Configuration options for the component responsible for interacting with the
@@ -77,16 +130,61 @@ pub struct ProverConfiguration {
     /// The number of seconds to wait before timing out the transaction.
     pub timeout_s: u64,
 
-    // TODO Add and query a prover `info` endpoint instead.
     /// The batch size that the prover is set up to work with. This must match
-    /// the deployed prover.
+    /// the deployed prover, which is checked against its `info` endpoint if
+    /// the prover exposes one.
     pub batch_size: usize,
 
-    // TODO: add docs
+    /// Whether the prover generates insertion or deletion proofs.
+    pub prover_type: ProverType,
+
+    /// How proofs are requested from the prover.
+    #[serde(default)]
+    pub protocol: ProverProtocol,
+
+    /// How requests to the prover are authenticated, if at all.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<ProverAuth>,
+}
+
+/// The protocol used to request proofs from a prover.
+#[derive(Debug, Copy, Clone, sqlx::Type, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+#[sqlx(type_name = "prover_protocol_enum", rename_all = "PascalCase")]
+pub enum ProverProtocol {
+    /// A single request to the `prove` endpoint that responds with the proof
+    /// once it has been generated.
+    #[default]
+    Sync,
+    /// A proving job is submitted to the `jobs` endpoint, and its status is
+    /// polled at `jobs/{jobId}` until the proof is ready. Proofs survive
+    /// dropped connections, and no request stays open for the whole duration
+    /// of the proof.
+    Async,
+}
+
+/// The circuit parameters reported by a prover's `info` endpoint.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProverInfo {
+    pub batch_size:  usize,
+    pub tree_depth:  usize,
     pub prover_type: ProverType,
 }
 
-#[derive(Debug, Copy, Clone, sqlx::Type, PartialEq, Eq, Serialize, Deserialize, Default)]
+/// The outcome of checking a prover's configuration against the circuit
+/// parameters it reports.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CapabilityCheck {
+    /// The prover reported parameters that match its configuration.
+    Compatible,
+    /// The prover could not be queried or doesn't expose an `info` endpoint.
+    Unknown,
+    /// The prover reported parameters that don't match its configuration.
+    Incompatible(String),
+}
+
+#[derive(Debug, Copy, Clone, sqlx::Type, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
 #[sqlx(type_name = "prover_enum", rename_all = "PascalCase")]
 pub enum ProverType {
@@ -95,20 +193,43 @@ pub enum ProverType {
     Deletion,
 }
 
+// Several replicas can serve the same batch size, so provers are identified by
+// their URL as well.
 impl Hash for ProverConfiguration {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.batch_size.hash(state);
+        self.prover_type.hash(state);
+        self.url.hash(state);
     }
 }
 
 impl PartialEq for ProverConfiguration {
     fn eq(&self, other: &Self) -> bool {
         self.batch_size == other.batch_size
+            && self.prover_type == other.prover_type
+            && self.url == other.url
     }
 }
 
 pub type Provers = HashSet<ProverConfiguration>;
 
+/// The response to the submission of an asynchronous proving job.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JobSubmission {
+    job_id: String,
+}
+
+/// The status of an asynchronous proving job.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+enum JobStatus {
+    Pending,
+    Running,
+    Completed { proof: Proof },
+    Failed { error: ProverError },
+}
+
 /// A representation of the connection to the MTB prover service.
 #[derive(Clone, Debug)]
 pub struct Prover {
@@ -117,6 +238,8 @@ pub struct Prover {
     batch_size:  usize,
     timeout_s:   u64,
     prover_type: ProverType,
+    protocol:    ProverProtocol,
+    auth:        Option<ProverAuth>,
 }
 
 impl Prover {
@@ -126,11 +249,7 @@ impl Prover {
     /// - `options`: The prover configuration options.
     pub fn new(options: &ProverConfiguration) -> anyhow::Result<Self> {
         let target_url = Url::parse(&options.url)?;
-        let timeout_duration = Duration::from_secs(options.timeout_s);
-        let client = reqwest::Client::builder()
-            .connect_timeout(timeout_duration)
-            .https_only(false)
-            .build()?;
+        let client = Self::build_client(options)?;
 
         let mtb = Self {
             target_url,
@@ -138,6 +257,8 @@ impl Prover {
             batch_size: options.batch_size,
             timeout_s: options.timeout_s,
             prover_type: options.prover_type,
+            protocol: options.protocol,
+            auth: options.auth.clone(),
         };
 
         Ok(mtb)
@@ -147,11 +268,7 @@ impl Prover {
     /// database
     pub fn from_prover_conf(prover_conf: &ProverConfiguration) -> anyhow::Result<Self> {
         let target_url = Url::parse(&prover_conf.url)?;
-        let timeout_duration = Duration::from_secs(prover_conf.timeout_s);
-        let client = reqwest::Client::builder()
-            .connect_timeout(timeout_duration)
-            .https_only(false)
-            .build()?;
+        let client = Self::build_client(prover_conf)?;
 
         Ok(Self {
             target_url,
@@ -159,9 +276,24 @@ impl Prover {
             batch_size: prover_conf.batch_size,
             timeout_s: prover_conf.timeout_s,
             prover_type: prover_conf.prover_type,
+            protocol: prover_conf.protocol,
+            auth: prover_conf.auth.clone(),
         })
     }
 
+    fn build_client(options: &ProverConfiguration) -> anyhow::Result<reqwest::Client> {
+        let timeout_duration = Duration::from_secs(options.timeout_s);
+        let mut builder = reqwest::Client::builder()
+            .connect_timeout(timeout_duration)
+            .https_only(false);
+
+        if let Some(auth) = &options.auth {
+            builder = auth.configure_client(builder)?;
+        }
+
+        Ok(builder.build()?)
+    }
+
     pub fn batch_size(&self) -> usize {
         self.batch_size
     }
@@ -174,6 +306,14 @@ impl Prover {
         self.timeout_s
     }
 
+    pub fn protocol(&self) -> ProverProtocol {
+        self.protocol
+    }
+
+    pub fn auth(&self) -> Option<&ProverAuth> {
+        self.auth.as_ref()
+    }
+
     /// Generates a proof term for the provided identity insertions into the
     /// merkle tree.
     ///
@@ -199,7 +339,9 @@ impl Prover {
             ));
         }
 
-        let total_proving_time_timer = TOTAL_PROVING_TIME.start_timer();
+        let [url, batch_size, prover_type] = self.metric_labels();
+        let labels = [url.as_str(), batch_size.as_str(), prover_type.as_str()];
+        let total_proving_time_timer = TOTAL_PROVING_TIME.with_label_values(&labels).start_timer();
 
         let identity_commitments: Vec<U256> = identities.iter().map(|id| id.commitment).collect();
         let input_hash = compute_insertion_proof_input_hash(
@@ -222,24 +364,12 @@ impl Prover {
             merkle_proofs,
         };
 
-        let request = self
-            .client
-            .post(self.target_url.join(MTB_PROVE_ENDPOINT)?)
-            .body("OH MY GOD")
-            .json(&proof_input)
-            .build()?;
-
-        let prover_proving_time_timer = PROVER_PROVING_TIME.start_timer();
-        let proof_term = self.client.execute(request).await?;
-        let proof_term = proof_term.error_for_status()?;
+        let prover_proving_time_timer =
+            PROVER_PROVING_TIME.with_label_values(&labels).start_timer();
+        let result = self.prove(&proof_input).await;
         prover_proving_time_timer.observe_duration();
-
-        let json = proof_term.text().await?;
-
-        let Ok(proof) = serde_json::from_str::<Proof>(&json) else {
-            let error: ProverError = serde_json::from_str(&json)?;
-            return Err(anyhow::Error::msg(format!("{error}")));
-        };
+        Self::record_outcome(&labels, &result);
+        let proof = result?;
 
         total_proving_time_timer.observe_duration();
 
@@ -259,7 +389,9 @@ impl Prover {
             ));
         }
 
-        let total_proving_time_timer = TOTAL_PROVING_TIME.start_timer();
+        let [url, batch_size, prover_type] = self.metric_labels();
+        let labels = [url.as_str(), batch_size.as_str(), prover_type.as_str()];
+        let total_proving_time_timer = TOTAL_PROVING_TIME.with_label_values(&labels).start_timer();
 
         let (identity_commitments, merkle_proofs): (Vec<U256>, Vec<Vec<U256>>) = identities
             .into_iter()
@@ -278,33 +410,238 @@ impl Prover {
             merkle_proofs,
         };
 
+        let prover_proving_time_timer =
+            PROVER_PROVING_TIME.with_label_values(&labels).start_timer();
+        let result = self.prove(&proof_input).await;
+        prover_proving_time_timer.observe_duration();
+        Self::record_outcome(&labels, &result);
+        let proof = result?;
+
+        total_proving_time_timer.observe_duration();
+
+        Ok(proof)
+    }
+
+    /// The values of [`PROVER_METRIC_LABELS`] for this prover.
+    fn metric_labels(&self) -> [String; 3] {
+        [
+            self.redacted_url(),
+            self.batch_size.to_string(),
+            format!("{:?}", self.prover_type),
+        ]
+    }
+
+    fn record_outcome(labels: &[&str; 3], result: &anyhow::Result<Proof>) {
+        let outcome = match result {
+            Ok(_) => "success",
+            Err(error) if error.downcast_ref::<ProverError>().is_some() => "rejected",
+            Err(_) => "error",
+        };
+
+        let [url, batch_size, prover_type] = *labels;
+        PROVER_REQUESTS
+            .with_label_values(&[url, batch_size, prover_type, outcome])
+            .inc();
+    }
+
+    /// Requests a proof for the provided `input` using the protocol of the
+    /// prover.
+    async fn prove(&self, input: &impl Serialize) -> anyhow::Result<Proof> {
+        match self.protocol {
+            ProverProtocol::Sync => self.prove_sync(input).await,
+            ProverProtocol::Async => self.prove_async(input).await,
+        }
+    }
+
+    async fn prove_sync(&self, input: &impl Serialize) -> anyhow::Result<Proof> {
         let request = self
             .client
             .post(self.target_url.join(MTB_PROVE_ENDPOINT)?)
-            .body("OH MY GOD")
-            .json(&proof_input)
-            .build()?;
+            .json(input);
 
-        let prover_proving_time_timer = PROVER_PROVING_TIME.start_timer();
-        let proof_term = self.client.execute(request).await?;
+        let proof_term = self.send(request).await?;
         let proof_term = proof_term.error_for_status()?;
-        prover_proving_time_timer.observe_duration();
 
         let json = proof_term.text().await?;
 
         let Ok(proof) = serde_json::from_str::<Proof>(&json) else {
             let error: ProverError = serde_json::from_str(&json)?;
-            return Err(anyhow::Error::msg(format!("{error}")));
+            return Err(error.into());
         };
 
-        total_proving_time_timer.observe_duration();
-
         Ok(proof)
     }
 
+    /// Submits a proving job and polls it until it completes or `timeout_s`
+    /// elapses. Failures to poll the job are retried, as the job keeps running
+    /// on the prover regardless.
+    async fn prove_async(&self, input: &impl Serialize) -> anyhow::Result<Proof> {
+        let timeout = Duration::from_secs(self.timeout_s);
+        let deadline = tokio::time::Instant::now() + timeout;
+
+        let request = self
+            .client
+            .post(self.target_url.join(MTB_JOBS_ENDPOINT)?)
+            .timeout(timeout)
+            .json(input);
+
+        let submission: JobSubmission =
+            self.send(request).await?.error_for_status()?.json().await?;
+
+        let job_id = submission.job_id;
+        let status_url = self
+            .target_url
+            .join(&format!("{MTB_JOBS_ENDPOINT}/{job_id}"))?;
+
+        info!(url = self.redacted_url(), job_id, "Submitted proving job.");
+
+        loop {
+            if tokio::time::Instant::now() >= deadline {
+                return Err(anyhow::anyhow!(
+                    "Proving job {job_id} did not complete within {} seconds.",
+                    self.timeout_s
+                ));
+            }
+
+            tokio::time::sleep(JOB_POLL_INTERVAL).await;
+
+            let status = match self.fetch_job_status(status_url.clone()).await {
+                Ok(status) => status,
+                Err(error) => {
+                    warn!(job_id, ?error, "Failed to poll proving job, retrying.");
+                    continue;
+                }
+            };
+
+            match status {
+                JobStatus::Pending | JobStatus::Running => continue,
+                JobStatus::Completed { proof } => return Ok(proof),
+                JobStatus::Failed { error } => return Err(error.into()),
+            }
+        }
+    }
+
+    async fn fetch_job_status(&self, status_url: Url) -> anyhow::Result<JobStatus> {
+        let request = self
+            .client
+            .get(status_url)
+            .timeout(Duration::from_secs(self.timeout_s));
+
+        let status = self.send(request).await?.error_for_status()?.json().await?;
+
+        Ok(status)
+    }
+
+    /// Sends the `request`, authenticating it if the prover requires it.
+    async fn send(&self, request: reqwest::RequestBuilder) -> anyhow::Result<reqwest::Response> {
+        let mut request = request.build()?;
+
+        if let Some(auth) = &self.auth {
+            auth.authenticate(&mut request)?;
+        }
+
+        Ok(self.client.execute(request).await?)
+    }
+
     pub fn url(&self) -> String {
         self.target_url.to_string()
     }
+
+    /// The URL of the prover with any credentials it contains redacted, for
+    /// use in logs, metrics and API responses.
+    pub fn redacted_url(&self) -> String {
+        let mut url = self.target_url.clone();
+        if !url.username().is_empty() || url.password().is_some() {
+            // Only fails for URLs that can't have credentials in the first place
+            let _ = url.set_password(None);
+            let _ = url.set_username("**********");
+        }
+        url.to_string()
+    }
+
+    /// Queries the circuit parameters of the prover.
+    ///
+    /// Returns `None` if the prover doesn't expose an `info` endpoint.
+    pub async fn info(&self) -> anyhow::Result<Option<ProverInfo>> {
+        let request = self
+            .client
+            .get(self.target_url.join(MTB_INFO_ENDPOINT)?)
+            .timeout(PROBE_TIMEOUT);
+
+        let response = self.send(request).await?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        let info = response.error_for_status()?.json().await?;
+
+        Ok(Some(info))
+    }
+
+    /// Checks the configuration of the prover against the circuit parameters
+    /// it reports and the depth of the tree it's going to be used with.
+    pub async fn check_capabilities(&self, tree_depth: usize) -> CapabilityCheck {
+        let info = match self.info().await {
+            Ok(Some(info)) => info,
+            Ok(None) => return CapabilityCheck::Unknown,
+            Err(error) => {
+                warn!(
+                    url = self.redacted_url(),
+                    ?error,
+                    "Failed to query prover info."
+                );
+                return CapabilityCheck::Unknown;
+            }
+        };
+
+        let mut mismatches = vec![];
+        if info.batch_size != self.batch_size {
+            mismatches.push(format!(
+                "batch size {} (configured {})",
+                info.batch_size, self.batch_size
+            ));
+        }
+        if info.tree_depth != tree_depth {
+            mismatches.push(format!(
+                "tree depth {} (configured {tree_depth})",
+                info.tree_depth
+            ));
+        }
+        if info.prover_type != self.prover_type {
+            mismatches.push(format!(
+                "prover type {:?} (configured {:?})",
+                info.prover_type, self.prover_type
+            ));
+        }
+
+        if mismatches.is_empty() {
+            CapabilityCheck::Compatible
+        } else {
+            CapabilityCheck::Incompatible(format!(
+                "prover at {} reports {}",
+                self.redacted_url(),
+                mismatches.join(", ")
+            ))
+        }
+    }
+
+    /// Checks whether the prover service can be reached at all. Any response,
+    /// even an error status, counts as reachable.
+    pub async fn is_reachable(&self) -> bool {
+        let request = self
+            .client
+            .get(self.target_url.clone())
+            .timeout(PROBE_TIMEOUT);
+
+        self.send(request).await.is_ok()
+    }
+}
+
+/// Normalizes `url` the same way the URLs of provers are, so that they can be
+/// compared.
+pub(crate) fn normalize_url(url: &str) -> String {
+    Url::parse(url).map_or_else(|_| url.to_string(), |url| url.to_string())
 }
 
 /// Computes the input hash to the prover.
@@ -398,6 +735,8 @@ impl Display for ProverError {
     }
 }
 
+impl std::error::Error for ProverError {}
+
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct InsertionProofInput {
@@ -434,6 +773,8 @@ mod test {
             timeout_s:   30,
             batch_size:  3,
             prover_type: ProverType::Insertion,
+            protocol:    ProverProtocol::Sync,
+            auth:        None,
         };
         let mtb = Prover::new(&options).unwrap();
         let input_data = get_default_proof_input();
@@ -456,6 +797,54 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn mtb_should_generate_proof_with_async_protocol() -> anyhow::Result<()> {
+        let mock_url: String = "0.0.0.0:3003".into();
+        let mock_service = mock::Service::new(mock_url.clone()).await?;
+
+        let options = ProverConfiguration {
+            url:         "http://localhost:3003".into(),
+            timeout_s:   30,
+            batch_size:  3,
+            prover_type: ProverType::Insertion,
+            protocol:    ProverProtocol::Async,
+            auth:        None,
+        };
+        let mtb = Prover::new(&options).unwrap();
+        let mut input_data = get_default_proof_input();
+        let identities: Vec<Identity> = extract_identities_from(&input_data);
+
+        let proof = mtb
+            .generate_insertion_proof(
+                input_data.start_index,
+                input_data.pre_root,
+                input_data.post_root,
+                &identities,
+            )
+            .await?;
+        assert_eq!(proof, get_default_proof_output());
+
+        // Failed jobs are reported as prover errors
+        input_data.post_root = U256::from(2);
+        let prover_result = mtb
+            .generate_insertion_proof(
+                input_data.start_index,
+                input_data.pre_root,
+                input_data.post_root,
+                &identities,
+            )
+            .await;
+
+        mock_service.stop();
+
+        assert!(prover_result
+            .expect_err("Job should fail")
+            .downcast_ref::<ProverError>()
+            .is_some());
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn mtb_should_respond_with_error_if_inputs_incorrect() -> anyhow::Result<()> {
         let mock_url: String = "0.0.0.0:3002".into();
@@ -466,6 +855,8 @@ mod test {
             timeout_s:   30,
             batch_size:  3,
             prover_type: ProverType::Insertion,
+            protocol:    ProverProtocol::Sync,
+            auth:        None,
         };
         let mtb = Prover::new(&options).unwrap();
         let mut input_data = get_default_proof_input();
@@ -494,6 +885,8 @@ mod test {
             timeout_s:   30,
             batch_size:  10,
             prover_type: ProverType::Insertion,
+            protocol:    ProverProtocol::Sync,
+            auth:        None,
         };
         let mtb = Prover::new(&options).unwrap();
         let input_data = get_default_proof_input();
@@ -674,11 +1067,15 @@ mod test {
 
 #[cfg(test)]
 pub mod mock {
+    use std::collections::HashMap;
     use std::net::SocketAddr;
+    use std::sync::{Arc, Mutex};
 
-    use axum::routing::post;
+    use axum::extract::{Path, State};
+    use axum::routing::{get, post};
     use axum::{Json, Router};
     use axum_server::Handle;
+    use hyper::StatusCode;
 
     use super::*;
 
@@ -686,6 +1083,11 @@ pub mod mock {
         server: Handle,
     }
 
+    /// The asynchronous proving jobs submitted to the mock. Jobs are completed
+    /// as soon as they are submitted, but are reported as running the first
+    /// time they are polled.
+    type Jobs = Arc<Mutex<HashMap<String, (bool, JobStatus)>>>;
+
     #[derive(Serialize, Deserialize)]
     #[serde(untagged)]
     #[allow(clippy::large_enum_variant)]
@@ -696,21 +1098,59 @@ pub mod mock {
 
     impl Service {
         pub async fn new(url: String) -> anyhow::Result<Self> {
-            let prove = |Json(payload): Json<InsertionProofInput>| async move {
+            fn respond(payload: &InsertionProofInput) -> ProveResponse {
                 match payload.post_root.div_mod(U256::from(2)) {
                     (_, y) if y != U256::zero() => {
-                        Json(ProveResponse::ProofSuccess(test::get_default_proof_output()))
-                    }
-                    _ => {
-                        let error = ProverError {
-                            code:    "Oh no!".into(),
-                            message: "Things went wrong.".into(),
-                        };
-                        Json(ProveResponse::ProofFailure(error))
+                        ProveResponse::ProofSuccess(test::get_default_proof_output())
                     }
+                    _ => ProveResponse::ProofFailure(ProverError {
+                        code:    "Oh no!".into(),
+                        message: "Things went wrong.".into(),
+                    }),
                 }
-            };
-            let app = Router::new().route("/prove", post(prove));
+            }
+
+            async fn submit_job(
+                State(jobs): State<Jobs>,
+                Json(payload): Json<InsertionProofInput>,
+            ) -> Json<JobSubmission> {
+                let status = match respond(&payload) {
+                    ProveResponse::ProofSuccess(proof) => JobStatus::Completed { proof },
+                    ProveResponse::ProofFailure(error) => JobStatus::Failed { error },
+                };
+
+                let mut jobs = jobs.lock().unwrap();
+                let job_id = jobs.len().to_string();
+                jobs.insert(job_id.clone(), (false, status));
+
+                Json(JobSubmission { job_id })
+            }
+
+            async fn job_status(
+                State(jobs): State<Jobs>,
+                Path(job_id): Path<String>,
+            ) -> Result<Json<JobStatus>, StatusCode> {
+                let mut jobs = jobs.lock().unwrap();
+                let (polled, status) = jobs.get_mut(&job_id).ok_or(StatusCode::NOT_FOUND)?;
+
+                if *polled {
+                    Ok(Json(status.clone()))
+                } else {
+                    *polled = true;
+                    Ok(Json(JobStatus::Running))
+                }
+            }
+
+            let prove =
+                |Json(payload): Json<InsertionProofInput>| async move { Json(respond(&payload)) };
+
+            let jobs = Jobs::default();
+
+            let app = Router::new()
+                .route("/prove", post(prove))
+                .route("/jobs", post(submit_job))
+                .route("/jobs/:job_id", get(job_status))
+                .with_state(jobs);
 
             let addr: SocketAddr = url.parse()?;
             let server = Handle::new();
@@ -0,0 +1,186 @@
+//! Local verification of the Groth16 proofs returned by the provers.
+//!
+//! Verifying a proof before it's submitted means that a bad proof from a buggy
+//! or compromised prover is caught before any gas is spent on a transaction
+//! that would revert.
+
+use std::collections::HashMap;
+use std::mem::size_of;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context};
+use ark_bn254::{Bn254, Fq, Fq2, Fr, G1Affine, G2Affine};
+use ark_ec::AffineCurve;
+use ark_ff::{BigInteger256, PrimeField};
+use ark_groth16::{prepare_verifying_key, verify_proof, PreparedVerifyingKey, VerifyingKey};
+use ethers::types::U256;
+use serde::{Deserialize, Serialize};
+
+use crate::prover::{Proof, ProverType};
+
+/// Where to load the verifying key for the proofs of a given batch size and
+/// prover type from.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerifyingKeyConfiguration {
+    pub batch_size:  usize,
+    pub prover_type: ProverType,
+    /// The path to a JSON file containing the verifying key.
+    pub path:        PathBuf,
+}
+
+/// A Groth16 verifying key, in the layout used by the `sol/*Verifier`
+/// contracts.
+///
+/// Elements of G2 are ordered the same way as in [`Proof::bs`], with the
+/// imaginary part of each coordinate first.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct VerifyingKeyFile {
+    alpha: [U256; 2],
+    beta:  [[U256; 2]; 2],
+    gamma: [[U256; 2]; 2],
+    delta: [[U256; 2]; 2],
+    /// The points used to accumulate the public inputs, starting with the
+    /// constant term.
+    ic:    Vec<[U256; 2]>,
+}
+
+/// Verifies the proofs of a single verifier contract.
+pub struct ProofVerifier {
+    key: PreparedVerifyingKey<Bn254>,
+}
+
+impl std::fmt::Debug for ProofVerifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProofVerifier").finish_non_exhaustive()
+    }
+}
+
+impl ProofVerifier {
+    /// Loads the verifying key stored at `path`.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let file = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read verifying key at {}", path.display()))?;
+        let file: VerifyingKeyFile = serde_json::from_str(&file)
+            .with_context(|| format!("Failed to parse verifying key at {}", path.display()))?;
+
+        // The provers only take the input hash as a public input
+        anyhow::ensure!(
+            file.ic.len() == 2,
+            "Verifying key at {} expects {} public inputs instead of 1.",
+            path.display(),
+            file.ic.len().saturating_sub(1)
+        );
+
+        let key = VerifyingKey {
+            alpha_g1:     g1_from_words(file.alpha)?,
+            beta_g2:      g2_from_words(file.beta)?,
+            gamma_g2:     g2_from_words(file.gamma)?,
+            delta_g2:     g2_from_words(file.delta)?,
+            gamma_abc_g1: file
+                .ic
+                .into_iter()
+                .map(g1_from_words)
+                .collect::<anyhow::Result<_>>()?,
+        };
+
+        Ok(Self {
+            key: prepare_verifying_key(&key),
+        })
+    }
+
+    /// Checks the `proof` against the verifying key, with the `input_hash`
+    /// computed by [`compute_insertion_proof_input_hash`] or
+    /// [`compute_deletion_proof_input_hash`] as the public input.
+    ///
+    /// The input hash is reduced into the scalar field the same way the
+    /// identity manager contract does.
+    ///
+    /// [`compute_insertion_proof_input_hash`]: super::compute_insertion_proof_input_hash
+    /// [`compute_deletion_proof_input_hash`]: super::compute_deletion_proof_input_hash
+    pub fn verify(&self, proof: &Proof, input_hash: U256) -> anyhow::Result<bool> {
+        let proof = ark_groth16::Proof {
+            a: g1_from_words(proof.ar)?,
+            b: g2_from_words(proof.bs)?,
+            c: g1_from_words(proof.krs)?,
+        };
+
+        let mut input_bytes = [0u8; size_of::<U256>()];
+        input_hash.to_big_endian(&mut input_bytes);
+        let input = Fr::from_be_bytes_mod_order(&input_bytes);
+
+        Ok(verify_proof(&self.key, &proof, &[input])?)
+    }
+}
+
+/// The verifiers for each batch size and prover type that has a verifying key
+/// configured.
+#[derive(Debug, Default)]
+pub struct ProofVerifiers {
+    verifiers: HashMap<(ProverType, usize), Arc<ProofVerifier>>,
+}
+
+impl ProofVerifiers {
+    /// Loads the verifying keys described by `configurations`.
+    pub fn load(configurations: &[VerifyingKeyConfiguration]) -> anyhow::Result<Self> {
+        let mut verifiers = HashMap::new();
+
+        for configuration in configurations {
+            let verifier = ProofVerifier::load(&configuration.path)?;
+            verifiers.insert(
+                (configuration.prover_type, configuration.batch_size),
+                Arc::new(verifier),
+            );
+        }
+
+        Ok(Self { verifiers })
+    }
+
+    #[must_use]
+    pub fn get(&self, prover_type: ProverType, batch_size: usize) -> Option<Arc<ProofVerifier>> {
+        self.verifiers.get(&(prover_type, batch_size)).cloned()
+    }
+}
+
+fn fq_from_word(word: U256) -> anyhow::Result<Fq> {
+    Fq::from_repr(BigInteger256::new(word.0))
+        .ok_or_else(|| anyhow!("Value {word} is not in the base field."))
+}
+
+fn g1_from_words([x, y]: [U256; 2]) -> anyhow::Result<G1Affine> {
+    let point = G1Affine::new(fq_from_word(x)?, fq_from_word(y)?, false);
+
+    anyhow::ensure!(point.is_on_curve(), "Point is not on the G1 curve.");
+
+    Ok(point)
+}
+
+fn g2_from_words([[x1, x0], [y1, y0]]: [[U256; 2]; 2]) -> anyhow::Result<G2Affine> {
+    let x = Fq2::new(fq_from_word(x0)?, fq_from_word(x1)?);
+    let y = Fq2::new(fq_from_word(y0)?, fq_from_word(y1)?);
+    let point = G2Affine::new(x, y, false);
+
+    anyhow::ensure!(
+        point.is_on_curve() && point.is_in_correct_subgroup_assuming_on_curve(),
+        "Point is not in the G2 subgroup."
+    );
+
+    Ok(point)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_points_off_the_curve() {
+        assert!(g1_from_words([U256::from(1), U256::from(3)]).is_err());
+        assert!(g1_from_words([U256::MAX, U256::from(2)]).is_err());
+    }
+
+    #[test]
+    fn accepts_the_g1_generator() {
+        let generator = g1_from_words([U256::from(1), U256::from(2)]).unwrap();
+        assert_eq!(generator, G1Affine::prime_subgroup_generator());
+    }
+}
@@ -37,6 +37,12 @@ pub enum Error {
     IdentityQueuedForDeletion,
     #[error("Identity has already been deleted.")]
     IdentityAlreadyDeleted,
+    #[error("Identity is not scheduled for a future insertion.")]
+    InsertionNotScheduled,
+    #[error("Identity has already been inserted into the tree.")]
+    IdentityAlreadyInserted,
+    #[error("Identity is not queued for deletion.")]
+    IdentityNotQueuedForDeletion,
     #[error("invalid JSON request: {0}")]
     InvalidSerialization(#[from] serde_json::Error),
     #[error(transparent)]
@@ -57,6 +63,10 @@ pub enum Error {
     BatchSizeAlreadyExists,
     #[error("The requested batch size does not exist")]
     NoSuchBatchSize,
+    #[error("The prover does not match the provided configuration: {0}")]
+    ProverCapabilityMismatch(String),
+    #[error("The provided URL matches several provers")]
+    AmbiguousProverUrl,
     #[error("The last batch size cannot be removed")]
     CannotRemoveLastBatchSize,
     #[error("Identity Manager had no provers on point of identity insertion.")]
@@ -100,10 +110,15 @@ impl Error {
             Self::InvalidContentType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
             Self::IndexOutOfBounds
             | Self::IdentityCommitmentNotFound
+            | Self::IdentityNotQueuedForDeletion
+            | Self::ProverCapabilityMismatch(_)
+            | Self::AmbiguousProverUrl
             | Self::InvalidCommitment
             | Self::InvalidSerialization(_) => StatusCode::BAD_REQUEST,
             Self::IdentityAlreadyDeleted
             | Self::IdentityQueuedForDeletion
+            | Self::IdentityAlreadyInserted
+            | Self::InsertionNotScheduled
             | Self::DuplicateCommitment => StatusCode::CONFLICT,
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         }
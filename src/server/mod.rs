@@ -8,6 +8,7 @@ use anyhow::{bail, ensure, Result as AnyhowResult};
 use axum::extract::{Query, State};
 use axum::routing::{get, post};
 use axum::{middleware, Json, Router};
+use chrono::{DateTime, Utc};
 use clap::Parser;
 use cli_batteries::await_shutdown;
 use error::Error;
@@ -19,10 +20,12 @@ use tracing::info;
 use url::{Host, Url};
 
 use crate::app::{
-    App, InclusionProofResponse, ListBatchSizesResponse, VerifySemaphoreProofResponse,
+    App, HealthResponse, InclusionProofResponse, InsertionStatusResponse, ListBatchSizesResponse,
+    VerifySemaphoreProofResponse,
 };
 use crate::identity_tree::Hash;
-use crate::prover::ProverType;
+use crate::prover::{ProverAuth, ProverProtocol, ProverType};
+use crate::task_monitor::health::TaskKind;
 
 mod custom_middleware;
 
@@ -44,6 +47,10 @@ pub struct Options {
 #[serde(deny_unknown_fields)]
 pub struct InsertCommitmentRequest {
     identity_commitment: Hash,
+    /// If provided, the identity will not be inserted into the tree before
+    /// this time.
+    #[serde(default)]
+    not_before:          Option<DateTime<Utc>>,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -56,8 +63,15 @@ pub struct AddBatchSizeRequest {
     batch_size:      usize,
     /// The timeout for communications with the prover service.
     timeout_seconds: u64,
-    // TODO: add docs
+    /// The type of the prover, either `insertion` or `deletion`.
     prover_type:     ProverType,
+    /// The protocol used to request proofs from the prover. Defaults to the
+    /// synchronous protocol.
+    #[serde(default)]
+    protocol:        ProverProtocol,
+    /// How requests to the prover are authenticated, if at all.
+    #[serde(default)]
+    auth:            Option<ProverAuth>,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -66,8 +80,21 @@ pub struct AddBatchSizeRequest {
 pub struct RemoveBatchSizeRequest {
     /// The batch size to remove from the prover map.
     batch_size:  usize,
-    // TODO: add docs
+    /// The type of the prover to remove, either `insertion` or `deletion`.
     prover_type: ProverType,
+    /// The URL of the prover replica to remove, either as it was added or as
+    /// it's listed by `/listBatchSizes`. If not set, all the provers for the
+    /// batch size are removed.
+    #[serde(default)]
+    url:         Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(deny_unknown_fields)]
+pub struct TaskRequest {
+    /// The background task to pause or resume.
+    task: TaskKind,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -77,6 +104,29 @@ pub struct InclusionProofRequest {
     pub identity_commitment: Hash,
 }
 
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(deny_unknown_fields)]
+pub struct CancelScheduledInsertionRequest {
+    /// The identity commitment whose scheduled insertion should be cancelled.
+    identity_commitment: Hash,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(deny_unknown_fields)]
+pub struct CancelInsertionRequest {
+    /// The identity commitment whose queued insertion should be cancelled.
+    identity_commitment: Hash,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(deny_unknown_fields)]
+pub struct InsertionStatusRequest {
+    pub identity_commitment: Hash,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 #[serde(deny_unknown_fields)]
@@ -104,6 +154,14 @@ pub struct DeletionRequest {
     identity_commitment: Hash,
 }
 
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(deny_unknown_fields)]
+pub struct CancelDeletionRequest {
+    /// The identity commitment whose queued deletion should be cancelled.
+    identity_commitment: Hash,
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 #[serde(deny_unknown_fields)]
@@ -141,12 +199,42 @@ async fn insert_identity(
     State(app): State<Arc<App>>,
     Json(insert_identity_request): Json<InsertCommitmentRequest>,
 ) -> Result<(), Error> {
-    app.insert_identity(insert_identity_request.identity_commitment)
+    app.insert_identity(
+        insert_identity_request.identity_commitment,
+        insert_identity_request.not_before,
+    )
+    .await?;
+
+    Ok(())
+}
+
+async fn cancel_scheduled_insertion(
+    State(app): State<Arc<App>>,
+    Json(req): Json<CancelScheduledInsertionRequest>,
+) -> Result<(), Error> {
+    app.cancel_scheduled_insertion(&req.identity_commitment)
         .await?;
 
     Ok(())
 }
 
+async fn cancel_insertion(
+    State(app): State<Arc<App>>,
+    Json(req): Json<CancelInsertionRequest>,
+) -> Result<(), Error> {
+    app.cancel_insertion(&req.identity_commitment).await?;
+    Ok(())
+}
+
+async fn insertion_status(
+    State(app): State<Arc<App>>,
+    Json(req): Json<InsertionStatusRequest>,
+) -> Result<(StatusCode, Json<InsertionStatusResponse>), Error> {
+    let result = app.insertion_status(&req.identity_commitment).await?;
+
+    Ok((result.to_response_code(), Json(result)))
+}
+
 async fn verify_semaphore_proof(
     State(app): State<Arc<App>>,
     Query(verify_semaphore_proof_query): Query<VerifySemaphoreProofQuery>,
@@ -173,6 +261,8 @@ async fn add_batch_size(
         req.batch_size,
         req.timeout_seconds,
         req.prover_type,
+        req.protocol,
+        req.auth,
     )
     .await?;
 
@@ -187,6 +277,14 @@ async fn delete_identity(
     Ok(())
 }
 
+async fn cancel_deletion(
+    State(app): State<Arc<App>>,
+    Json(req): Json<CancelDeletionRequest>,
+) -> Result<(), Error> {
+    app.cancel_deletion(&req.identity_commitment).await?;
+    Ok(())
+}
+
 async fn recover_identity(
     State(app): State<Arc<App>>,
     Json(req): Json<RecoveryRequest>,
@@ -203,7 +301,7 @@ async fn remove_batch_size(
     State(app): State<Arc<App>>,
     Json(req): Json<RemoveBatchSizeRequest>,
 ) -> Result<(), Error> {
-    app.remove_batch_size(req.batch_size, req.prover_type)
+    app.remove_batch_size(req.batch_size, req.prover_type, req.url.as_deref())
         .await?;
 
     Ok(())
@@ -215,6 +313,35 @@ async fn list_batch_sizes(
 
     Ok((result.to_response_code(), Json(result)))
 }
+
+async fn pause_task(
+    State(app): State<Arc<App>>,
+    Json(req): Json<TaskRequest>,
+) -> Result<(), Error> {
+    app.pause_task(req.task).await?;
+    Ok(())
+}
+
+async fn resume_task(
+    State(app): State<Arc<App>>,
+    Json(req): Json<TaskRequest>,
+) -> Result<(), Error> {
+    app.resume_task(req.task).await?;
+    Ok(())
+}
+
+async fn health_live(State(app): State<Arc<App>>) -> (StatusCode, Json<HealthResponse>) {
+    let result = app.liveness().await;
+
+    (result.to_response_code(), Json(result))
+}
+
+async fn health_ready(State(app): State<Arc<App>>) -> (StatusCode, Json<HealthResponse>) {
+    let result = app.readiness().await;
+
+    (result.to_response_code(), Json(result))
+}
+
 /// # Errors
 ///
 /// Will return `Err` if `options.server` URI is not http, incorrectly includes
@@ -263,11 +390,22 @@ pub async fn bind_from_listener(
         .route("/verifySemaphoreProof", post(verify_semaphore_proof))
         .route("/inclusionProof", post(inclusion_proof))
         .route("/insertIdentity", post(insert_identity))
+        .route(
+            "/cancelScheduledInsertion",
+            post(cancel_scheduled_insertion),
+        )
+        .route("/cancelInsertion", post(cancel_insertion))
+        .route("/insertionStatus", post(insertion_status))
         .route("/addBatchSize", post(add_batch_size))
         .route("/deleteIdentity", post(delete_identity))
+        .route("/cancelDeletion", post(cancel_deletion))
         .route("/recoverIdentity", post(recover_identity))
         .route("/removeBatchSize", post(remove_batch_size))
         .route("/listBatchSizes", get(list_batch_sizes))
+        .route("/pauseTask", post(pause_task))
+        .route("/resumeTask", post(resume_task))
+        .route("/health/live", get(health_live))
+        .route("/health/ready", get(health_ready))
         .layer(middleware::from_fn(
             custom_middleware::api_metrics_layer::middleware,
         ))
@@ -5,25 +5,34 @@ use anyhow::Result as AnyhowResult;
 use clap::Parser;
 use once_cell::sync::Lazy;
 use prometheus::{linear_buckets, register_gauge, register_histogram, Gauge, Histogram};
-use tokio::sync::{broadcast, mpsc, Notify, RwLock};
+use tokio::sync::{broadcast, mpsc, Mutex, MutexGuard, Notify, RwLock};
 use tokio::task::JoinHandle;
 use tracing::{info, instrument, warn};
 
+use self::health::{TaskHealth, TaskHealthRegistry, TaskHealthReport, TaskKind};
 use self::tasks::delete_identities::DeleteIdentities;
 use self::tasks::finalize_identities::FinalizeRoots;
 use self::tasks::insert_identities::InsertIdentities;
 use self::tasks::monitor_txs::MonitorTxs;
+use self::tasks::probe_provers::ProbeProvers;
 use self::tasks::process_identities::ProcessIdentities;
 use crate::contracts::SharedIdentityManager;
 use crate::database::Database;
 use crate::identity_tree::TreeState;
 
+pub mod health;
 pub mod tasks;
 
 const PROCESS_IDENTITIES_BACKOFF: Duration = Duration::from_secs(5);
 const FINALIZE_IDENTITIES_BACKOFF: Duration = Duration::from_secs(5);
 const INSERT_IDENTITIES_BACKOFF: Duration = Duration::from_secs(5);
 const DELETE_IDENTITIES_BACKOFF: Duration = Duration::from_secs(5);
+const PROBE_PROVERS_BACKOFF: Duration = Duration::from_secs(5);
+
+/// How often the provers are probed for their circuit parameters and health.
+/// Shorter than the circuit breaker cooldown, so that recovered provers are
+/// picked up soon after it elapses.
+const PROVER_PROBE_INTERVAL: Duration = Duration::from_secs(10);
 
 struct RunningInstance {
     handles:         Vec<JoinHandle<()>>,
@@ -107,9 +116,32 @@ pub struct Options {
     #[clap(long, env, default_value = "30")]
     pub time_between_scans_seconds: u64,
 
+    /// The number of blocks a log must be buried under before it's used for
+    /// finalization. Chain reorgs within the scanned blocks are detected and
+    /// rolled back either way.
+    ///
+    /// Batches removed from mainnet by a reorg are not sent again. Their
+    /// identities become pending, and if the batches aren't mined again within
+    /// 15 minutes the finalization task is reported as degraded. The sequencer
+    /// then has to be restarted to batch them again, so the depth should cover
+    /// the reorgs expected on the chain.
+    #[clap(long, env, default_value = "0")]
+    pub confirmation_depth: u64,
+
     /// The number of txs in the channel that we'll be monitoring
     #[clap(long, env, default_value = "100")]
     pub monitored_txs_capacity: usize,
+
+    /// The maximum number of seconds the insertion and deletion tasks wait
+    /// before checking their queues again if they haven't been woken up by a
+    /// new request in the meantime
+    #[clap(long, env, default_value = "60")]
+    pub queue_poll_interval_seconds: u64,
+
+    /// The number of seconds a task may go without completing an iteration,
+    /// on top of its regular wait interval, before it's reported as stalled
+    #[clap(long, env, default_value = "300")]
+    pub task_stall_grace_seconds: u64,
 }
 
 /// A worker that commits identities to the blockchain.
@@ -124,6 +156,14 @@ pub struct TaskMonitor {
     /// await the join handles - which requires ownership of the handle and by
     /// extension the instance.
     instance:                  RwLock<Option<RunningInstance>>,
+    /// Held while queued insertions or deletions are being moved into the
+    /// latest tree, so that they can't be cancelled half-way through.
+    queues_lock:               Arc<Mutex<()>>,
+    /// Wakes up the insertion task when a new identity is queued.
+    insertion_notify:          Arc<Notify>,
+    /// Wakes up the deletion task when a new deletion is queued.
+    deletion_notify:           Arc<Notify>,
+    health:                    TaskHealthRegistry,
     database:                  Arc<Database>,
     identity_manager:          SharedIdentityManager,
     tree_state:                TreeState,
@@ -132,12 +172,15 @@ pub struct TaskMonitor {
     // Finalization params
     scanning_window_size:           u64,
     time_between_scans:             Duration,
+    confirmation_depth:             u64,
     max_epoch_duration:             Duration,
     // TODO: docs
     batch_deletion_timeout_seconds: i64,
     // TODO: docs
     min_batch_deletion_size:        usize,
     monitored_txs_capacity:         usize,
+    queue_poll_interval:            Duration,
+    task_stall_grace:               Duration,
 }
 
 impl TaskMonitor {
@@ -151,34 +194,50 @@ impl TaskMonitor {
             batch_timeout_seconds,
             scanning_window_size,
             time_between_scans_seconds,
+            confirmation_depth,
             max_epoch_duration_seconds,
             monitored_txs_capacity,
             batch_deletion_timeout_seconds,
             min_batch_deletion_size,
+            queue_poll_interval_seconds,
+            task_stall_grace_seconds,
         } = *options;
 
         Self {
             instance: RwLock::new(None),
+            queues_lock: Arc::new(Mutex::new(())),
+            insertion_notify: Arc::new(Notify::new()),
+            deletion_notify: Arc::new(Notify::new()),
+            health: TaskHealthRegistry::default(),
             database,
             identity_manager: contracts,
             tree_state,
             batch_insert_timeout_secs: batch_timeout_seconds,
             scanning_window_size,
             time_between_scans: Duration::from_secs(time_between_scans_seconds),
+            confirmation_depth,
             batch_deletion_timeout_seconds,
             min_batch_deletion_size,
             max_epoch_duration: Duration::from_secs(max_epoch_duration_seconds),
             monitored_txs_capacity,
+            queue_poll_interval: Duration::from_secs(queue_poll_interval_seconds),
+            task_stall_grace: Duration::from_secs(task_stall_grace_seconds),
         }
     }
 
+    /// # Errors
+    ///
+    /// Will return an Error if the paused tasks can't be loaded from the
+    /// database.
     #[instrument(level = "debug", skip_all)]
-    pub async fn start(&self) {
+    pub async fn start(&self) -> AnyhowResult<()> {
         let mut instance = self.instance.write().await;
         if instance.is_some() {
             warn!("Identity committer already running");
         }
 
+        let paused_tasks = self.database.get_paused_tasks().await?;
+
         // We could use the second element of the tuple as `mut shutdown_receiver`,
         // but for symmetry's sake we create it for every task with `.subscribe()`
         let (shutdown_sender, _) = broadcast::channel(1);
@@ -194,6 +253,11 @@ impl TaskMonitor {
         let mut handles = Vec::new();
 
         // Finalize identities task
+        let finalize_identities_health = self.register_task(
+            TaskKind::FinalizeIdentities,
+            Some(self.time_between_scans + self.task_stall_grace),
+            &paused_tasks,
+        );
         let finalize_identities = FinalizeRoots::new(
             self.database.clone(),
             self.identity_manager.clone(),
@@ -201,18 +265,27 @@ impl TaskMonitor {
             self.tree_state.get_mined_tree(),
             self.scanning_window_size,
             self.time_between_scans,
+            self.confirmation_depth,
             self.max_epoch_duration,
+            self.insertion_notify.clone(),
+            finalize_identities_health.clone(),
         );
 
         let finalize_identities_handle = crate::utils::spawn_monitored_with_backoff(
             move || finalize_identities.clone().run(),
             shutdown_sender.clone(),
             FINALIZE_IDENTITIES_BACKOFF,
+            finalize_identities_health,
         );
 
         handles.push(finalize_identities_handle);
 
         // Process identities task
+        let process_identities_health = self.register_task(
+            TaskKind::ProcessIdentities,
+            Some(Duration::from_secs(self.batch_insert_timeout_secs) + self.task_stall_grace),
+            &paused_tasks,
+        );
         let process_identities = ProcessIdentities::new(
             self.database.clone(),
             self.identity_manager.clone(),
@@ -220,62 +293,192 @@ impl TaskMonitor {
             self.batch_insert_timeout_secs,
             monitored_txs_sender,
             wake_up_notify.clone(),
+            process_identities_health.clone(),
         );
 
         let process_identities_handle = crate::utils::spawn_monitored_with_backoff(
             move || process_identities.clone().run(),
             shutdown_sender.clone(),
             PROCESS_IDENTITIES_BACKOFF,
+            process_identities_health,
         );
 
         handles.push(process_identities_handle);
 
-        let monitor_txs = MonitorTxs::new(self.identity_manager.clone(), monitored_txs_receiver);
+        // The monitor txs task is idle until a transaction is submitted, so it can't
+        // stall
+        let monitor_txs_health = self.register_task(TaskKind::MonitorTxs, None, &paused_tasks);
+        let monitor_txs = MonitorTxs::new(
+            self.database.clone(),
+            self.identity_manager.clone(),
+            monitored_txs_receiver,
+            monitor_txs_health.clone(),
+        );
 
         let monitor_txs_handle = crate::utils::spawn_monitored_with_backoff(
             move || monitor_txs.clone().run(),
             shutdown_sender.clone(),
             PROCESS_IDENTITIES_BACKOFF,
+            monitor_txs_health,
         );
 
         handles.push(monitor_txs_handle);
 
         // Insert identities task
+        let insert_identities_health = self.register_task(
+            TaskKind::InsertIdentities,
+            Some(self.queue_poll_interval + self.task_stall_grace),
+            &paused_tasks,
+        );
         let insert_identities = InsertIdentities::new(
             self.database.clone(),
             self.tree_state.get_latest_tree(),
+            self.queues_lock.clone(),
+            self.insertion_notify.clone(),
+            self.queue_poll_interval,
             wake_up_notify.clone(),
+            insert_identities_health.clone(),
         );
 
         let insert_identities_handle = crate::utils::spawn_monitored_with_backoff(
             move || insert_identities.clone().run(),
             shutdown_sender.clone(),
             INSERT_IDENTITIES_BACKOFF,
+            insert_identities_health,
         );
 
         handles.push(insert_identities_handle);
 
         // Delete identities task
+        let delete_identities_health = self.register_task(
+            TaskKind::DeleteIdentities,
+            Some(self.queue_poll_interval + self.task_stall_grace),
+            &paused_tasks,
+        );
         let delete_identities = DeleteIdentities::new(
             self.database.clone(),
             self.tree_state.get_latest_tree(),
             self.batch_deletion_timeout_seconds,
             self.min_batch_deletion_size,
+            self.queues_lock.clone(),
+            self.deletion_notify.clone(),
+            self.queue_poll_interval,
             wake_up_notify,
+            delete_identities_health.clone(),
         );
 
         let delete_identities_handle = crate::utils::spawn_monitored_with_backoff(
             move || delete_identities.clone().run(),
             shutdown_sender.clone(),
             DELETE_IDENTITIES_BACKOFF,
+            delete_identities_health,
         );
 
         handles.push(delete_identities_handle);
 
+        // Probe provers task
+        let probe_provers_health = self.register_task(
+            TaskKind::ProbeProvers,
+            Some(PROVER_PROBE_INTERVAL + self.task_stall_grace),
+            &paused_tasks,
+        );
+        let probe_provers = ProbeProvers::new(
+            self.identity_manager.clone(),
+            PROVER_PROBE_INTERVAL,
+            probe_provers_health.clone(),
+        );
+
+        let probe_provers_handle = crate::utils::spawn_monitored_with_backoff(
+            move || probe_provers.clone().run(),
+            shutdown_sender.clone(),
+            PROBE_PROVERS_BACKOFF,
+            probe_provers_health,
+        );
+
+        handles.push(probe_provers_handle);
+
         *instance = Some(RunningInstance {
             handles,
             shutdown_sender,
         });
+
+        Ok(())
+    }
+
+    /// Pauses a task once it completes its current iteration. The paused state
+    /// is persisted, so the task stays paused across restarts.
+    ///
+    /// # Errors
+    ///
+    /// Will return an Error if the paused state can't be persisted.
+    pub async fn pause_task(&self, task: TaskKind) -> AnyhowResult<()> {
+        self.database.pause_task(task).await?;
+        self.set_paused(task, true);
+        Ok(())
+    }
+
+    /// # Errors
+    ///
+    /// Will return an Error if the paused state can't be persisted.
+    pub async fn resume_task(&self, task: TaskKind) -> AnyhowResult<()> {
+        self.database.resume_task(task).await?;
+        self.set_paused(task, false);
+        Ok(())
+    }
+
+    fn register_task(
+        &self,
+        task: TaskKind,
+        stall_timeout: Option<Duration>,
+        paused_tasks: &[TaskKind],
+    ) -> Arc<TaskHealth> {
+        let health = self.health.register(task, stall_timeout);
+
+        if paused_tasks.contains(&task) {
+            warn!(task = task.name(), "Task is paused.");
+            health.pause();
+        }
+
+        health
+    }
+
+    fn set_paused(&self, task: TaskKind, paused: bool) {
+        // Tasks that haven't been started yet pick up the paused state from the
+        // database
+        let Some(health) = self.health.get(task) else {
+            return;
+        };
+
+        if paused {
+            health.pause();
+        } else {
+            health.resume();
+        }
+    }
+
+    /// Acquires the lock that prevents queued insertions and deletions from
+    /// being moved into the latest tree while it's held.
+    pub async fn lock_queues(&self) -> MutexGuard<'_, ()> {
+        self.queues_lock.lock().await
+    }
+
+    /// Returns the health of the tasks, or `None` if the monitor isn't running.
+    pub async fn task_health(&self) -> Option<Vec<TaskHealthReport>> {
+        if self.instance.read().await.is_none() {
+            return None;
+        }
+
+        Some(self.health.reports())
+    }
+
+    /// Wakes up the insertion task after a new identity has been queued.
+    pub fn notify_queued_insertion(&self) {
+        self.insertion_notify.notify_one();
+    }
+
+    /// Wakes up the deletion task after a new deletion has been queued.
+    pub fn notify_queued_deletion(&self) {
+        self.deletion_notify.notify_one();
     }
 
     async fn log_pending_identities_count(database: &Database) -> AnyhowResult<()> {
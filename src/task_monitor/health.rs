@@ -0,0 +1,404 @@
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use prometheus::{
+    register_gauge_vec, register_int_counter_vec, register_int_gauge_vec, GaugeVec, IntCounterVec,
+    IntGaugeVec,
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+
+static TASK_RUNNING: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "task_running",
+        "Whether a background task is currently running.",
+        &["task"]
+    )
+    .unwrap()
+});
+
+static TASK_RESTARTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "task_restarts",
+        "Number of times a background task failed and was restarted.",
+        &["task"]
+    )
+    .unwrap()
+});
+
+static TASK_LAST_SUCCESS: Lazy<GaugeVec> = Lazy::new(|| {
+    register_gauge_vec!(
+        "task_last_success_timestamp_seconds",
+        "Unix timestamp of the last successful iteration of a background task.",
+        &["task"]
+    )
+    .unwrap()
+});
+
+/// The tasks spawned by the [`TaskMonitor`](super::TaskMonitor).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskKind {
+    FinalizeIdentities,
+    ProcessIdentities,
+    MonitorTxs,
+    InsertIdentities,
+    DeleteIdentities,
+    ProbeProvers,
+}
+
+impl TaskKind {
+    pub const ALL: [Self; 6] = [
+        Self::FinalizeIdentities,
+        Self::ProcessIdentities,
+        Self::MonitorTxs,
+        Self::InsertIdentities,
+        Self::DeleteIdentities,
+        Self::ProbeProvers,
+    ];
+
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::FinalizeIdentities => "finalize_identities",
+            Self::ProcessIdentities => "process_identities",
+            Self::MonitorTxs => "monitor_txs",
+            Self::InsertIdentities => "insert_identities",
+            Self::DeleteIdentities => "delete_identities",
+            Self::ProbeProvers => "probe_provers",
+        }
+    }
+
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|task| task.name() == name)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TaskState {
+    /// The task has been registered but not spawned yet.
+    Starting,
+    Running,
+    /// The task failed and is waiting for its backoff to elapse.
+    Restarting,
+    Stopped,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskHealthReport {
+    pub name:          TaskKind,
+    pub state:         TaskState,
+    /// Whether the task has been paused by an operator.
+    pub paused:        bool,
+    /// Whether the task is running but hasn't completed an iteration for
+    /// longer than its stall timeout.
+    pub stalled:       bool,
+    pub last_success:  Option<DateTime<Utc>>,
+    pub restart_count: u64,
+    pub last_error:    Option<String>,
+    /// Why the task can't fully recover on its own, if it can't.
+    pub degraded:      Option<String>,
+}
+
+impl TaskHealthReport {
+    /// A task is healthy if it's running and making progress.
+    #[must_use]
+    pub fn is_healthy(&self) -> bool {
+        self.state == TaskState::Running && !self.stalled && self.degraded.is_none()
+    }
+
+    /// A task is alive as long as it hasn't stopped or stalled. Tasks that are
+    /// restarting are still considered to be alive.
+    #[must_use]
+    pub fn is_alive(&self) -> bool {
+        self.state != TaskState::Stopped && !self.stalled
+    }
+}
+
+struct TaskHealthInner {
+    state:         TaskState,
+    /// The last time the task was started or resumed.
+    started_at:    DateTime<Utc>,
+    last_success:  Option<DateTime<Utc>>,
+    restart_count: u64,
+    last_error:    Option<String>,
+    degraded:      Option<String>,
+}
+
+/// Keeps track of the health of a single background task.
+///
+/// The task runner updates the state as the task is (re)started, and the task
+/// itself calls [`Self::heartbeat`] every time it completes an iteration of its
+/// main loop. Tasks also call [`Self::wait_until_resumed`] before doing any
+/// work, which allows operators to pause them.
+pub struct TaskHealth {
+    task:          TaskKind,
+    stall_timeout: Option<Duration>,
+    inner:         Mutex<TaskHealthInner>,
+    paused:        watch::Sender<bool>,
+}
+
+impl TaskHealth {
+    /// Creates the health record of a task. If `stall_timeout` is set the task
+    /// is reported as stalled if it doesn't call [`Self::heartbeat`] for that
+    /// long.
+    #[must_use]
+    pub fn new(task: TaskKind, stall_timeout: Option<Duration>) -> Self {
+        TASK_RUNNING.with_label_values(&[task.name()]).set(0);
+
+        let (paused, _) = watch::channel(false);
+
+        Self {
+            task,
+            stall_timeout,
+            paused,
+            inner: Mutex::new(TaskHealthInner {
+                state:         TaskState::Starting,
+                started_at:    Utc::now(),
+                last_success:  None,
+                restart_count: 0,
+                last_error:    None,
+                degraded:      None,
+            }),
+        }
+    }
+
+    pub fn running(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.state = TaskState::Running;
+        inner.started_at = Utc::now();
+
+        TASK_RUNNING.with_label_values(&[self.task.name()]).set(1);
+    }
+
+    /// Records a successful iteration of the task.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn heartbeat(&self) {
+        let now = Utc::now();
+        self.inner.lock().unwrap().last_success = Some(now);
+
+        TASK_LAST_SUCCESS
+            .with_label_values(&[self.task.name()])
+            .set(now.timestamp() as f64);
+    }
+
+    pub fn failed(&self, error: String) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.state = TaskState::Restarting;
+        inner.restart_count += 1;
+        inner.last_error = Some(error);
+
+        TASK_RUNNING.with_label_values(&[self.task.name()]).set(0);
+        TASK_RESTARTS.with_label_values(&[self.task.name()]).inc();
+    }
+
+    /// Reports the task as unhealthy until [`Self::recovered`] is called,
+    /// without restarting it.
+    pub fn degraded(&self, reason: String) {
+        self.inner.lock().unwrap().degraded = Some(reason);
+    }
+
+    pub fn recovered(&self) {
+        self.inner.lock().unwrap().degraded = None;
+    }
+
+    pub fn stopped(&self) {
+        self.inner.lock().unwrap().state = TaskState::Stopped;
+
+        TASK_RUNNING.with_label_values(&[self.task.name()]).set(0);
+    }
+
+    pub fn pause(&self) {
+        self.paused.send_replace(true);
+    }
+
+    pub fn resume(&self) {
+        // Don't count the time spent paused towards the stall timeout
+        self.inner.lock().unwrap().started_at = Utc::now();
+        self.paused.send_replace(false);
+    }
+
+    #[must_use]
+    pub fn is_paused(&self) -> bool {
+        *self.paused.borrow()
+    }
+
+    /// Returns once the task is not paused.
+    pub async fn wait_until_resumed(&self) {
+        let mut paused = self.paused.subscribe();
+
+        // The sender is owned by `self`, so this can't fail
+        _ = paused.wait_for(|paused| !paused).await;
+    }
+
+    #[must_use]
+    pub fn report(&self) -> TaskHealthReport {
+        let inner = self.inner.lock().unwrap();
+        let paused = self.is_paused();
+
+        let stalled = match self.stall_timeout {
+            Some(stall_timeout) if inner.state == TaskState::Running && !paused => {
+                let last_progress = inner.last_success.map_or(inner.started_at, |last_success| {
+                    last_success.max(inner.started_at)
+                });
+                let since_last_progress = (Utc::now() - last_progress)
+                    .to_std()
+                    .unwrap_or(Duration::ZERO);
+
+                since_last_progress > stall_timeout
+            }
+            _ => false,
+        };
+
+        TaskHealthReport {
+            name: self.task,
+            state: inner.state,
+            paused,
+            stalled,
+            last_success: inner.last_success,
+            restart_count: inner.restart_count,
+            last_error: inner.last_error.clone(),
+            degraded: inner.degraded.clone(),
+        }
+    }
+}
+
+/// The health records of all the tasks spawned by the
+/// [`TaskMonitor`](super::TaskMonitor).
+#[derive(Default)]
+pub struct TaskHealthRegistry {
+    tasks: RwLock<Vec<Arc<TaskHealth>>>,
+}
+
+impl TaskHealthRegistry {
+    /// Registers a task, replacing any previous record of the same task.
+    pub fn register(&self, task: TaskKind, stall_timeout: Option<Duration>) -> Arc<TaskHealth> {
+        let health = Arc::new(TaskHealth::new(task, stall_timeout));
+
+        let mut tasks = self.tasks.write().unwrap();
+        tasks.retain(|existing| existing.task != task);
+        tasks.push(health.clone());
+
+        health
+    }
+
+    #[must_use]
+    pub fn get(&self, task: TaskKind) -> Option<Arc<TaskHealth>> {
+        self.tasks
+            .read()
+            .unwrap()
+            .iter()
+            .find(|existing| existing.task == task)
+            .cloned()
+    }
+
+    #[must_use]
+    pub fn reports(&self) -> Vec<TaskHealthReport> {
+        self.tasks
+            .read()
+            .unwrap()
+            .iter()
+            .map(|task| task.report())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn task_health_tracks_restarts() {
+        let health = TaskHealth::new(TaskKind::InsertIdentities, None);
+        assert_eq!(health.report().state, TaskState::Starting);
+
+        health.running();
+        health.heartbeat();
+        assert!(health.report().is_healthy());
+
+        health.failed("boom".to_string());
+        let report = health.report();
+        assert_eq!(report.state, TaskState::Restarting);
+        assert_eq!(report.restart_count, 1);
+        assert_eq!(report.last_error.as_deref(), Some("boom"));
+        assert!(!report.is_healthy());
+        assert!(report.is_alive());
+
+        health.stopped();
+        assert!(!health.report().is_alive());
+    }
+
+    #[test]
+    fn degraded_tasks_are_not_healthy() {
+        let health = TaskHealth::new(TaskKind::FinalizeIdentities, None);
+        health.running();
+
+        health.degraded("restart required".to_string());
+        let report = health.report();
+        assert_eq!(report.degraded.as_deref(), Some("restart required"));
+        assert!(!report.is_healthy());
+        assert!(report.is_alive());
+
+        health.recovered();
+        assert!(health.report().is_healthy());
+    }
+
+    #[test]
+    fn task_health_detects_stalls() {
+        let health = TaskHealth::new(TaskKind::DeleteIdentities, Some(Duration::ZERO));
+        health.running();
+        std::thread::sleep(Duration::from_millis(10));
+
+        let report = health.report();
+        assert!(report.stalled);
+        assert!(!report.is_healthy());
+        assert!(!report.is_alive());
+    }
+
+    #[tokio::test]
+    async fn paused_tasks_wait_until_resumed() {
+        let health = TaskHealth::new(TaskKind::ProcessIdentities, Some(Duration::ZERO));
+        health.running();
+        health.pause();
+        std::thread::sleep(Duration::from_millis(10));
+
+        // Paused tasks are not reported as stalled
+        let report = health.report();
+        assert!(report.paused);
+        assert!(!report.stalled);
+
+        let waiting = tokio::time::timeout(Duration::from_millis(50), health.wait_until_resumed());
+        assert!(waiting.await.is_err());
+
+        health.resume();
+        tokio::time::timeout(Duration::from_millis(50), health.wait_until_resumed())
+            .await
+            .expect("Task should be resumed");
+        assert!(!health.report().paused);
+    }
+
+    #[test]
+    fn task_kinds_round_trip_through_their_names() {
+        for task in TaskKind::ALL {
+            assert_eq!(TaskKind::from_name(task.name()), Some(task));
+        }
+    }
+
+    #[test]
+    fn registry_replaces_tasks_with_the_same_name() {
+        let registry = TaskHealthRegistry::default();
+        registry
+            .register(TaskKind::FinalizeIdentities, None)
+            .failed("boom".to_string());
+        registry.register(TaskKind::FinalizeIdentities, None);
+
+        let reports = registry.reports();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].restart_count, 0);
+    }
+}
@@ -1,21 +1,27 @@
 use std::collections::HashSet;
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::Result as AnyhowResult;
 use chrono::Utc;
-use tokio::sync::Notify;
+use tokio::sync::{Mutex, Notify};
 use tracing::info;
 
 use crate::database::types::DeletionEntry;
 use crate::database::Database;
 use crate::identity_tree::{Hash, Latest, TreeVersion};
+use crate::task_monitor::health::TaskHealth;
 
 pub struct DeleteIdentities {
     database:                Arc<Database>,
     latest_tree:             TreeVersion<Latest>,
     deletion_time_interval:  i64,
     min_deletion_batch_size: usize,
+    queues_lock:             Arc<Mutex<()>>,
+    queue_notify:            Arc<Notify>,
+    poll_interval:           Duration,
     wake_up_notify:          Arc<Notify>,
+    health:                  Arc<TaskHealth>,
 }
 
 impl DeleteIdentities {
@@ -24,14 +30,22 @@ impl DeleteIdentities {
         latest_tree: TreeVersion<Latest>,
         deletion_time_interval: i64,
         min_deletion_batch_size: usize,
+        queues_lock: Arc<Mutex<()>>,
+        queue_notify: Arc<Notify>,
+        poll_interval: Duration,
         wake_up_notify: Arc<Notify>,
+        health: Arc<TaskHealth>,
     ) -> Arc<Self> {
         Arc::new(Self {
             database,
             latest_tree,
             deletion_time_interval,
             min_deletion_batch_size,
+            queues_lock,
+            queue_notify,
+            poll_interval,
             wake_up_notify,
+            health,
         })
     }
 
@@ -41,37 +55,53 @@ impl DeleteIdentities {
             &self.latest_tree,
             self.deletion_time_interval,
             self.min_deletion_batch_size,
+            &self.queues_lock,
+            &self.queue_notify,
+            self.poll_interval,
             self.wake_up_notify.clone(),
+            &self.health,
         )
         .await
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn delete_identities(
     database: &Database,
     latest_tree: &TreeVersion<Latest>,
     deletion_time_interval: i64,
     min_deletion_batch_size: usize,
+    queues_lock: &Mutex<()>,
+    queue_notify: &Notify,
+    poll_interval: Duration,
     wake_up_notify: Arc<Notify>,
+    health: &TaskHealth,
 ) -> AnyhowResult<()> {
     info!("Starting deletion processor.");
 
     let deletion_time_interval = chrono::Duration::seconds(deletion_time_interval);
 
     loop {
+        health.heartbeat();
+        health.wait_until_resumed().await;
+
+        // Hold the lock until the deletions are applied to the tree so that they
+        // can't be cancelled in the meantime
+        let queues_guard = queues_lock.lock().await;
+
         let deletions = database.get_deletions().await?;
         if deletions.is_empty() {
-            tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+            drop(queues_guard);
+            _ = tokio::time::timeout(poll_interval, queue_notify.notified()).await;
             continue;
         }
 
         let last_deletion_timestamp = database.get_latest_deletion().await?.timestamp;
+        let next_deletion_timestamp = last_deletion_timestamp + deletion_time_interval;
 
         // If the minimum deletions batch size is reached or the deletion time interval
         // has elapsed, run a batch of deletions
-        if deletions.len() >= min_deletion_batch_size
-            || Utc::now() - last_deletion_timestamp > deletion_time_interval
-        {
+        if deletions.len() >= min_deletion_batch_size || Utc::now() > next_deletion_timestamp {
             // Dedup deletion entries
             let deletions = deletions.into_iter().collect::<HashSet<DeletionEntry>>();
 
@@ -100,7 +130,20 @@ async fn delete_identities(
 
             // Remove the previous commitments from the deletions table
             database.remove_deletions(previous_commitments).await?;
+            drop(queues_guard);
+
             wake_up_notify.notify_one();
+        } else {
+            drop(queues_guard);
+
+            // Sleep until more deletions are queued or the deletion time interval
+            // elapses
+            let until_next_deletion = (next_deletion_timestamp - Utc::now())
+                .to_std()
+                .unwrap_or(Duration::ZERO);
+            let timeout = poll_interval.min(until_next_deletion);
+
+            _ = tokio::time::timeout(timeout, queue_notify.notified()).await;
         }
     }
 }
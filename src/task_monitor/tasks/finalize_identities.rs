@@ -1,20 +1,25 @@
 use std::collections::HashMap;
-use std::sync::Arc;
-use std::time::Duration;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-use anyhow::{Context, Result as AnyhowResult};
+use anyhow::{anyhow, Context, Result as AnyhowResult};
 use chrono::Utc;
 use ethers::abi::RawLog;
 use ethers::contract::EthEvent;
 use ethers::providers::Middleware;
 use ethers::types::{Address, Log, Topic, ValueOrArray, U256};
-use tracing::{info, instrument};
+use semaphore::poseidon_tree::LazyPoseidonTree;
+use tokio::sync::Notify;
+use tracing::{error, info, instrument, warn};
 
 use crate::contracts::abi::{BridgedWorldId, RootAddedFilter, TreeChangeKind, TreeChangedFilter};
 use crate::contracts::scanner::BlockScanner;
 use crate::contracts::{IdentityManager, SharedIdentityManager};
 use crate::database::Database;
-use crate::identity_tree::{Canonical, Intermediate, TreeVersion, TreeWithNextVersion};
+use crate::identity_tree::{
+    Canonical, Hash, Intermediate, Status, TreeVersion, TreeVersionReadOps, TreeWithNextVersion,
+};
+use crate::task_monitor::health::TaskHealth;
 use crate::task_monitor::TaskMonitor;
 
 pub struct FinalizeRoots {
@@ -25,7 +30,27 @@ pub struct FinalizeRoots {
 
     scanning_window_size: u64,
     time_between_scans:   Duration,
+    confirmation_depth:   u64,
     max_epoch_duration:   Duration,
+
+    /// Wakes up the insertion task when recoveries are queued.
+    insertion_notify: Arc<Notify>,
+    health:           Arc<TaskHealth>,
+
+    /// Batches dropped by a mainnet reorg, kept across task restarts.
+    dropped_batches: Mutex<Option<DroppedBatches>>,
+}
+
+/// How long batches dropped by a reorg may take to be mined again before the
+/// task is reported as degraded.
+const DROPPED_BATCHES_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
+/// Batches that were removed from mainnet by a reorg.
+#[derive(Debug)]
+struct DroppedBatches {
+    /// The processed root before the reorg.
+    root:       Hash,
+    dropped_at: Instant,
 }
 
 impl FinalizeRoots {
@@ -36,7 +61,10 @@ impl FinalizeRoots {
         finalized_tree: TreeVersion<Canonical>,
         scanning_window_size: u64,
         time_between_scans: Duration,
+        confirmation_depth: u64,
         max_epoch_duration: Duration,
+        insertion_notify: Arc<Notify>,
+        health: Arc<TaskHealth>,
     ) -> Arc<Self> {
         Arc::new(Self {
             database,
@@ -45,7 +73,11 @@ impl FinalizeRoots {
             finalized_tree,
             scanning_window_size,
             time_between_scans,
+            confirmation_depth,
             max_epoch_duration,
+            insertion_notify,
+            health,
+            dropped_batches: Mutex::new(None),
         })
     }
 
@@ -57,12 +89,17 @@ impl FinalizeRoots {
             &self.finalized_tree,
             self.scanning_window_size,
             self.time_between_scans,
+            self.confirmation_depth,
             self.max_epoch_duration,
+            &self.insertion_notify,
+            &self.health,
+            &self.dropped_batches,
         )
         .await
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn finalize_roots_loop(
     database: &Database,
     identity_manager: &IdentityManager,
@@ -70,19 +107,58 @@ async fn finalize_roots_loop(
     finalized_tree: &TreeVersion<Canonical>,
     scanning_window_size: u64,
     time_between_scans: Duration,
+    confirmation_depth: u64,
     max_epoch_duration: Duration,
+    insertion_notify: &Notify,
+    health: &TaskHealth,
+    dropped_batches: &Mutex<Option<DroppedBatches>>,
 ) -> AnyhowResult<()> {
     let mainnet_abi = identity_manager.abi();
     let secondary_abis = identity_manager.secondary_abis();
 
-    let mut mainnet_scanner =
-        BlockScanner::new_latest(mainnet_abi.client().clone(), scanning_window_size).await?;
+    let mut mainnet_scanner = BlockScanner::new_latest(
+        mainnet_abi.client().clone(),
+        scanning_window_size,
+        confirmation_depth,
+    )
+    .await?;
     let mut secondary_scanners =
-        init_secondary_scanners(secondary_abis, scanning_window_size).await?;
+        init_secondary_scanners(secondary_abis, scanning_window_size, confirmation_depth).await?;
 
     let mainnet_address = mainnet_abi.address();
 
     loop {
+        health.wait_until_resumed().await;
+
+        if let Some(fork_block) = mainnet_scanner.detect_reorg().await? {
+            let dropped = rollback_mainnet_roots(
+                database,
+                identity_manager,
+                processed_tree,
+                finalized_tree,
+                fork_block,
+            )
+            .await?;
+
+            if let Some(dropped) = dropped {
+                // Batches dropped by an earlier reorg go up to a later root
+                dropped_batches.lock().unwrap().get_or_insert(dropped);
+            }
+        }
+
+        for (address, scanner) in &mut secondary_scanners {
+            if let Some(fork_block) = scanner.detect_reorg().await? {
+                rollback_secondary_roots(
+                    database,
+                    identity_manager,
+                    finalized_tree,
+                    *address,
+                    fork_block,
+                )
+                .await?;
+            }
+        }
+
         let mainnet_logs = fetch_mainnet_logs(&mut mainnet_scanner, mainnet_address).await?;
 
         finalize_mainnet_roots(
@@ -91,6 +167,7 @@ async fn finalize_roots_loop(
             processed_tree,
             &mainnet_logs,
             max_epoch_duration,
+            insertion_notify,
         )
         .await?;
 
@@ -99,6 +176,10 @@ async fn finalize_roots_loop(
 
         finalize_secondary_roots(database, identity_manager, finalized_tree, roots).await?;
 
+        check_dropped_batches(identity_manager, dropped_batches, health).await?;
+
+        health.heartbeat();
+
         tokio::time::sleep(time_between_scans).await;
     }
 }
@@ -163,6 +244,7 @@ async fn finalize_mainnet_roots(
     processed_tree: &TreeVersion<Intermediate>,
     logs: &[Log],
     max_epoch_duration: Duration,
+    insertion_notify: &Notify,
 ) -> Result<(), anyhow::Error> {
     for log in logs {
         let Some(event) = raw_log_to_tree_changed(log) else {
@@ -194,6 +276,7 @@ async fn finalize_mainnet_roots(
                 processed_tree,
                 &log,
                 max_epoch_duration,
+                insertion_notify,
             )
             .await?;
         }
@@ -208,6 +291,176 @@ async fn finalize_mainnet_roots(
     Ok(())
 }
 
+/// Rolls the roots back to the latest root as of `fork_block`, the last
+/// scanned block that is still part of the chain after a reorg.
+///
+/// The identities of the removed roots become pending again. Their batches are
+/// not sent again: they're finalized again if their transactions are mined
+/// again, otherwise a restart is required to batch them again. Returns the
+/// dropped batches, if the reorg removed any.
+#[instrument(
+    level = "info",
+    skip(database, identity_manager, processed_tree, finalized_tree)
+)]
+async fn rollback_mainnet_roots(
+    database: &Database,
+    identity_manager: &IdentityManager,
+    processed_tree: &TreeVersion<Intermediate>,
+    finalized_tree: &TreeVersion<Canonical>,
+    fork_block: u64,
+) -> anyhow::Result<Option<DroppedBatches>> {
+    let root: Hash = identity_manager.latest_root_at(fork_block).await?.into();
+
+    rollback_roots_to(
+        database,
+        processed_tree,
+        finalized_tree,
+        root,
+        initial_root(identity_manager),
+    )
+    .await
+}
+
+async fn rollback_roots_to(
+    database: &Database,
+    processed_tree: &TreeVersion<Intermediate>,
+    finalized_tree: &TreeVersion<Canonical>,
+    root: Hash,
+    initial_root: Hash,
+) -> anyhow::Result<Option<DroppedBatches>> {
+    warn!(?root, "Rolling back roots after chain reorg");
+
+    let processed_root = processed_tree.get_root();
+
+    database
+        .rollback_roots_after((root != initial_root).then_some(&root))
+        .await?;
+
+    // Rewinding the mined tree rewinds the processed tree with it
+    let rewound_count = finalized_tree.rewind_to(root).ok_or_else(|| {
+        anyhow!("Cannot rewind the trees to root {root:?}, a restart is required")
+    })?;
+
+    info!(rewound_count, ?root, "Roots rolled back");
+
+    TaskMonitor::log_identities_queues(database).await?;
+
+    let dropped = (processed_root != root).then(|| DroppedBatches {
+        root:       processed_root,
+        dropped_at: Instant::now(),
+    });
+
+    Ok(dropped)
+}
+
+/// Rolls the mined roots back to the latest root of the bridged contract at
+/// `address` as of `fork_block`, after a reorg on its chain. The roots stay
+/// processed, and are finalized again once they're bridged again.
+#[instrument(level = "info", skip(database, identity_manager, finalized_tree))]
+async fn rollback_secondary_roots(
+    database: &Database,
+    identity_manager: &IdentityManager,
+    finalized_tree: &TreeVersion<Canonical>,
+    address: Address,
+    fork_block: u64,
+) -> anyhow::Result<()> {
+    let root = identity_manager
+        .secondary_latest_root_at(address, fork_block)
+        .await?;
+    let initial_root = initial_root(identity_manager);
+
+    // Bridged contracts have no root until the first one is bridged
+    let root = if root.is_zero() {
+        initial_root
+    } else {
+        root.into()
+    };
+
+    rollback_mined_roots_to(database, finalized_tree, root, initial_root).await
+}
+
+async fn rollback_mined_roots_to(
+    database: &Database,
+    finalized_tree: &TreeVersion<Canonical>,
+    root: Hash,
+    initial_root: Hash,
+) -> anyhow::Result<()> {
+    if root != initial_root {
+        let root_state = database
+            .get_root_state(&root)
+            .await?
+            .ok_or_else(|| anyhow!("Unknown root {root:?} on secondary chain"))?;
+
+        // Nothing after the root was mined yet
+        if root_state.status != Status::Mined {
+            return Ok(());
+        }
+    }
+
+    warn!(
+        ?root,
+        "Rolling back mined roots after secondary chain reorg"
+    );
+
+    database
+        .rollback_mined_roots_after((root != initial_root).then_some(&root))
+        .await?;
+
+    let rewound_count = finalized_tree.rewind_mined_to(root).ok_or_else(|| {
+        anyhow!("Cannot rewind the mined tree to root {root:?}, a restart is required")
+    })?;
+
+    info!(rewound_count, ?root, "Mined roots rolled back");
+
+    Ok(())
+}
+
+/// Reports the task as degraded while batches dropped by a reorg haven't been
+/// mined again for too long. Nothing sends them again, so only a restart
+/// recovers from that.
+async fn check_dropped_batches(
+    identity_manager: &IdentityManager,
+    dropped_batches: &Mutex<Option<DroppedBatches>>,
+    health: &TaskHealth,
+) -> anyhow::Result<()> {
+    let Some((root, dropped_at)) = dropped_batches
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|dropped| (dropped.root, dropped.dropped_at))
+    else {
+        return Ok(());
+    };
+
+    if identity_manager.is_root_mined(root.into()).await? {
+        info!(?root, "Batches dropped by a reorg were mined again");
+
+        *dropped_batches.lock().unwrap() = None;
+        health.recovered();
+    } else if dropped_at.elapsed() > DROPPED_BATCHES_TIMEOUT {
+        error!(
+            ?root,
+            "Batches dropped by a reorg were not mined again, a restart is required to batch them \
+             again"
+        );
+
+        health.degraded(format!(
+            "Batches up to root {root:?} were dropped by a reorg, a restart is required"
+        ));
+    }
+
+    Ok(())
+}
+
+/// The initial root is not stored in the database.
+fn initial_root(identity_manager: &IdentityManager) -> Hash {
+    LazyPoseidonTree::new(
+        identity_manager.tree_depth(),
+        identity_manager.initial_leaf_value(),
+    )
+    .root()
+}
+
 #[instrument(level = "info", skip_all)]
 async fn finalize_secondary_roots(
     database: &Database,
@@ -235,6 +488,7 @@ async fn finalize_secondary_roots(
 async fn init_secondary_scanners<T>(
     providers: &[BridgedWorldId<T>],
     scanning_window_size: u64,
+    confirmation_depth: u64,
 ) -> anyhow::Result<HashMap<Address, BlockScanner<Arc<T>>>>
 where
     T: Middleware,
@@ -243,8 +497,12 @@ where
     let mut secondary_scanners = HashMap::new();
 
     for bridged_abi in providers {
-        let scanner =
-            BlockScanner::new_latest(bridged_abi.client().clone(), scanning_window_size).await?;
+        let scanner = BlockScanner::new_latest(
+            bridged_abi.client().clone(),
+            scanning_window_size,
+            confirmation_depth,
+        )
+        .await?;
 
         let address = bridged_abi.address();
 
@@ -287,14 +545,13 @@ fn extract_roots_from_secondary_logs(logs: &[Log]) -> Vec<U256> {
     roots
 }
 
-use crate::identity_tree::Hash;
-
 async fn update_eligible_recoveries(
     database: &Database,
     identity_manager: &IdentityManager,
     processed_tree: &TreeVersion<Intermediate>,
     log: &Log,
     max_epoch_duration: Duration,
+    insertion_notify: &Notify,
 ) -> anyhow::Result<()> {
     let tx_hash = log.transaction_hash.context("Missing tx hash")?;
     let commitments = identity_manager
@@ -330,13 +587,127 @@ async fn update_eligible_recoveries(
 
     // For each deletion, if there is a corresponding recovery, insert a new
     // identity with the specified eligibility timestamp
+    let mut queued_recoveries = false;
     for prev_commitment in commitments {
         if let Some(new_commitment) = recoveries.get(&prev_commitment.into()) {
             database
                 .insert_new_identity(*new_commitment, eligibility_timestamp)
                 .await?;
+            queued_recoveries = true;
         }
     }
 
+    if queued_recoveries {
+        insertion_notify.notify_one();
+    }
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use postgres_docker_utils::DockerContainerGuard;
+
+    use super::*;
+    use crate::database::Options;
+    use crate::identity_tree::CanonicalTreeBuilder;
+    use crate::secret::SecretUrl;
+
+    async fn setup_db() -> anyhow::Result<(Database, DockerContainerGuard)> {
+        let db_container = postgres_docker_utils::setup().await?;
+        let db_socket_addr = db_container.address();
+        let url = format!("postgres://postgres:postgres@{db_socket_addr}/database");
+
+        let db = Database::new(Options {
+            database:                 SecretUrl::from_str(&url)?,
+            database_migrate:         true,
+            database_max_connections: 1,
+        })
+        .await?;
+
+        Ok((db, db_container))
+    }
+
+    async fn assert_root_is(db: &Database, root: &Hash, status: Status) -> anyhow::Result<()> {
+        let root_state = db.get_root_state(root).await?.context("Missing root")?;
+        assert_eq!(root_state.status, status, "Status of root {root:?}");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reorgs_roll_back_roots_and_trees() -> anyhow::Result<()> {
+        let (db, _db_container) = setup_db().await?;
+
+        let (mined_tree, processed_builder) =
+            CanonicalTreeBuilder::new(10, 10, 0, Hash::ZERO, &[]).seal();
+        let (processed_tree, batching_builder) = processed_builder.seal_and_continue();
+        let (batching_tree, latest_builder) = batching_builder.seal_and_continue();
+        let latest_tree = latest_builder.seal();
+
+        let initial_root = mined_tree.get_root();
+        let identities = [Hash::from(1), Hash::from(2), Hash::from(3), Hash::from(4)];
+        let roots: Vec<Hash> = latest_tree
+            .append_many(&identities)
+            .into_iter()
+            .map(|(root, ..)| root)
+            .collect();
+
+        for (leaf_index, (identity, root)) in identities.iter().zip(&roots).enumerate() {
+            db.insert_pending_identity(leaf_index, identity, root)
+                .await?;
+        }
+
+        batching_tree.apply_updates_up_to(roots[3]);
+        processed_tree.apply_updates_up_to(roots[3]);
+        db.mark_root_as_processed(&roots[3]).await?;
+        mined_tree.apply_updates_up_to(roots[1]);
+        db.mark_root_as_mined(&roots[1]).await?;
+
+        // A root that isn't mined yet leaves the mined roots as they are
+        rollback_mined_roots_to(&db, &mined_tree, roots[2], initial_root).await?;
+        assert_eq!(mined_tree.get_root(), roots[1]);
+        assert_root_is(&db, &roots[1], Status::Mined).await?;
+
+        // A secondary chain reorg only rolls back the mined roots
+        rollback_mined_roots_to(&db, &mined_tree, roots[0], initial_root).await?;
+        assert_eq!(mined_tree.get_root(), roots[0]);
+        assert_eq!(processed_tree.get_root(), roots[3]);
+        assert_root_is(&db, &roots[0], Status::Mined).await?;
+        assert_root_is(&db, &roots[1], Status::Processed).await?;
+        assert_root_is(&db, &roots[3], Status::Processed).await?;
+
+        // A mainnet reorg rolls back the processed roots and reports the
+        // dropped batches
+        let dropped =
+            rollback_roots_to(&db, &processed_tree, &mined_tree, roots[1], initial_root).await?;
+        assert_eq!(dropped.map(|dropped| dropped.root), Some(roots[3]));
+        assert_eq!(mined_tree.get_root(), roots[0]);
+        assert_eq!(processed_tree.get_root(), roots[1]);
+        assert_eq!(batching_tree.get_root(), roots[3]);
+        assert_root_is(&db, &roots[1], Status::Processed).await?;
+        assert_root_is(&db, &roots[2], Status::Pending).await?;
+        assert_root_is(&db, &roots[3], Status::Pending).await?;
+
+        // Nothing is dropped if the processed roots are still on chain
+        let dropped =
+            rollback_roots_to(&db, &processed_tree, &mined_tree, roots[1], initial_root).await?;
+        assert!(dropped.is_none());
+
+        rollback_roots_to(
+            &db,
+            &processed_tree,
+            &mined_tree,
+            initial_root,
+            initial_root,
+        )
+        .await?;
+        assert_eq!(mined_tree.get_root(), initial_root);
+        assert_eq!(processed_tree.get_root(), initial_root);
+        assert_root_is(&db, &roots[0], Status::Pending).await?;
+
+        Ok(())
+    }
+}
@@ -3,54 +3,103 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use anyhow::Result as AnyhowResult;
-use tokio::sync::Notify;
-use tokio::time::sleep;
+use chrono::Utc;
+use tokio::sync::{Mutex, Notify};
 use tracing::instrument;
 
 use crate::database::types::UnprocessedCommitment;
 use crate::database::Database;
 use crate::identity_tree::{Hash, Latest, Status, TreeVersion, TreeVersionReadOps};
+use crate::task_monitor::health::TaskHealth;
 
 pub struct InsertIdentities {
     database:       Arc<Database>,
     latest_tree:    TreeVersion<Latest>,
+    queues_lock:    Arc<Mutex<()>>,
+    queue_notify:   Arc<Notify>,
+    poll_interval:  Duration,
     wake_up_notify: Arc<Notify>,
+    health:         Arc<TaskHealth>,
 }
 
 impl InsertIdentities {
     pub fn new(
         database: Arc<Database>,
         latest_tree: TreeVersion<Latest>,
+        queues_lock: Arc<Mutex<()>>,
+        queue_notify: Arc<Notify>,
+        poll_interval: Duration,
         wake_up_notify: Arc<Notify>,
+        health: Arc<TaskHealth>,
     ) -> Arc<Self> {
         Arc::new(Self {
             database,
             latest_tree,
+            queues_lock,
+            queue_notify,
+            poll_interval,
             wake_up_notify,
+            health,
         })
     }
 
     pub async fn run(self: Arc<Self>) -> anyhow::Result<()> {
-        insert_identities_loop(&self.database, &self.latest_tree, &self.wake_up_notify).await
+        insert_identities_loop(
+            &self.database,
+            &self.latest_tree,
+            &self.queues_lock,
+            &self.queue_notify,
+            self.poll_interval,
+            &self.wake_up_notify,
+            &self.health,
+        )
+        .await
     }
 }
 
 async fn insert_identities_loop(
     database: &Database,
     latest_tree: &TreeVersion<Latest>,
+    queues_lock: &Mutex<()>,
+    queue_notify: &Notify,
+    poll_interval: Duration,
     wake_up_notify: &Notify,
+    health: &TaskHealth,
 ) -> AnyhowResult<()> {
     loop {
+        health.heartbeat();
+        health.wait_until_resumed().await;
+
+        // Hold the lock until the identities are in the tree so that they can't be
+        // cancelled in the meantime
+        let queues_guard = queues_lock.lock().await;
+
         // get commits from database
         let unprocessed = database
             .get_eligible_unprocessed_commitments(Status::New)
             .await?;
         if unprocessed.is_empty() {
-            sleep(Duration::from_secs(5)).await;
+            drop(queues_guard);
+
+            // Sleep until a new identity is queued, but wake up in time for scheduled
+            // insertions to become eligible
+            let mut timeout = poll_interval;
+            if let Some(next_eligibility) =
+                database.get_next_eligibility_timestamp(Status::New).await?
+            {
+                let until_eligible = (next_eligibility - Utc::now())
+                    .to_std()
+                    .unwrap_or(Duration::ZERO);
+                timeout = timeout.min(until_eligible);
+            }
+
+            _ = tokio::time::timeout(timeout, queue_notify.notified()).await;
             continue;
         }
 
         insert_identities(database, latest_tree, unprocessed).await?;
+        drop(queues_guard);
+
         // Notify the identity processing task, that there are new identities
         wake_up_notify.notify_one();
     }
@@ -2,4 +2,5 @@ pub mod delete_identities;
 pub mod finalize_identities;
 pub mod insert_identities;
 pub mod monitor_txs;
+pub mod probe_provers;
 pub mod process_identities;
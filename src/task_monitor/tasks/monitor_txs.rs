@@ -1,44 +1,183 @@
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use anyhow::Result as AnyhowResult;
 use tokio::sync::{mpsc, Mutex};
+use tracing::{info, warn};
 
 use crate::contracts::{IdentityManager, SharedIdentityManager};
+use crate::database::types::{SubmittedTransaction, SubmittedTransactionStatus};
+use crate::database::Database;
 use crate::ethereum::write::TransactionId;
+use crate::task_monitor::health::TaskHealth;
+
+/// How long to wait for the post root of a batch that can't be monitored by
+/// its transaction id to appear on chain.
+const ROOT_MINED_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+const ROOT_MINED_POLL_INTERVAL: Duration = Duration::from_secs(10);
 
 pub struct MonitorTxs {
+    database:               Arc<Database>,
     identity_manager:       SharedIdentityManager,
     monitored_txs_receiver: Arc<Mutex<mpsc::Receiver<TransactionId>>>,
+    health:                 Arc<TaskHealth>,
 }
 
 impl MonitorTxs {
     pub fn new(
+        database: Arc<Database>,
         identity_manager: SharedIdentityManager,
         monitored_txs_receiver: mpsc::Receiver<TransactionId>,
+        health: Arc<TaskHealth>,
     ) -> Arc<Self> {
         Arc::new(Self {
+            database,
             identity_manager,
             monitored_txs_receiver: Arc::new(Mutex::new(monitored_txs_receiver)),
+            health,
         })
     }
 
     pub async fn run(self: Arc<Self>) -> anyhow::Result<()> {
-        monitor_txs_loop(&self.identity_manager, &self.monitored_txs_receiver).await?;
+        resume_submitted_txs(&self.database, &self.identity_manager, &self.health).await?;
+
+        monitor_txs_loop(
+            &self.database,
+            &self.identity_manager,
+            &self.monitored_txs_receiver,
+            &self.health,
+        )
+        .await?;
 
         Ok(())
     }
 }
 
+/// Monitors the transactions that were sent before the last restart and are
+/// not known to be mined yet.
+///
+/// Transactions sent through a different write provider can't be found by
+/// their id, and batches that were recorded but not handed to the write
+/// provider don't have one, so whether they were mined is decided by their
+/// post root instead.
+async fn resume_submitted_txs(
+    database: &Database,
+    identity_manager: &IdentityManager,
+    health: &TaskHealth,
+) -> AnyhowResult<()> {
+    let submitted_txs = database.get_pending_submitted_transactions().await?;
+
+    if !submitted_txs.is_empty() {
+        info!(
+            count = submitted_txs.len(),
+            "Resuming monitoring of submitted transactions"
+        );
+    }
+
+    for submitted_tx in submitted_txs {
+        health.wait_until_resumed().await;
+
+        let mined = match &submitted_tx.transaction_id {
+            Some(transaction_id) => {
+                let tx = TransactionId(transaction_id.clone());
+
+                match identity_manager.mine_transaction(tx.clone()).await {
+                    Ok(mined) => mined,
+                    Err(error) => {
+                        warn!(
+                            %tx,
+                            ?error,
+                            "Failed to monitor submitted transaction, checking its post root \
+                             instead"
+                        );
+
+                        wait_for_root(identity_manager, &submitted_tx, health).await?
+                    }
+                }
+            }
+            None => {
+                info!(
+                    id = submitted_tx.id,
+                    "Batch has no transaction id, checking its post root"
+                );
+
+                wait_for_root(identity_manager, &submitted_tx, health).await?
+            }
+        };
+
+        if !mined {
+            database
+                .set_submitted_batch_status(submitted_tx.id, SubmittedTransactionStatus::Failed)
+                .await?;
+
+            panic!("Failed to mine submitted batch: {}", submitted_tx.id);
+        }
+
+        database
+            .set_submitted_batch_status(submitted_tx.id, SubmittedTransactionStatus::Mined)
+            .await?;
+
+        health.heartbeat();
+    }
+
+    Ok(())
+}
+
+/// Polls for the post root of a submitted batch until it's mined or
+/// [`ROOT_MINED_TIMEOUT`] elapses, since the transaction may still be in
+/// flight.
+async fn wait_for_root(
+    identity_manager: &IdentityManager,
+    submitted_tx: &SubmittedTransaction,
+    health: &TaskHealth,
+) -> AnyhowResult<bool> {
+    let deadline = Instant::now() + ROOT_MINED_TIMEOUT;
+
+    loop {
+        if identity_manager
+            .is_root_mined(submitted_tx.post_root.into())
+            .await?
+        {
+            return Ok(true);
+        }
+
+        if Instant::now() >= deadline {
+            warn!(
+                id = submitted_tx.id,
+                "Post root of submitted batch did not appear on chain"
+            );
+            return Ok(false);
+        }
+
+        health.heartbeat();
+        tokio::time::sleep(ROOT_MINED_POLL_INTERVAL).await;
+    }
+}
+
 async fn monitor_txs_loop(
+    database: &Database,
     identity_manager: &IdentityManager,
     monitored_txs_receiver: &Mutex<mpsc::Receiver<TransactionId>>,
+    health: &TaskHealth,
 ) -> AnyhowResult<()> {
     let mut monitored_txs_receiver = monitored_txs_receiver.lock().await;
 
     while let Some(tx) = monitored_txs_receiver.recv().await {
+        health.wait_until_resumed().await;
+
         if !identity_manager.mine_transaction(tx.clone()).await? {
+            database
+                .set_submitted_transaction_status(tx.as_ref(), SubmittedTransactionStatus::Failed)
+                .await?;
+
             panic!("Failed to mine transaction: {}", tx);
         }
+
+        database
+            .set_submitted_transaction_status(tx.as_ref(), SubmittedTransactionStatus::Mined)
+            .await?;
+
+        health.heartbeat();
     }
 
     Ok(())
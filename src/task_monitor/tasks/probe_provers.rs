@@ -0,0 +1,37 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::contracts::SharedIdentityManager;
+use crate::task_monitor::health::TaskHealth;
+
+pub struct ProbeProvers {
+    identity_manager: SharedIdentityManager,
+    probe_interval:   Duration,
+    health:           Arc<TaskHealth>,
+}
+
+impl ProbeProvers {
+    pub fn new(
+        identity_manager: SharedIdentityManager,
+        probe_interval: Duration,
+        health: Arc<TaskHealth>,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            identity_manager,
+            probe_interval,
+            health,
+        })
+    }
+
+    pub async fn run(self: Arc<Self>) -> anyhow::Result<()> {
+        loop {
+            self.health.wait_until_resumed().await;
+
+            self.identity_manager.probe_provers().await;
+
+            self.health.heartbeat();
+
+            tokio::time::sleep(self.probe_interval).await;
+        }
+    }
+}
@@ -4,6 +4,8 @@ use std::time::Duration;
 use anyhow::{Context, Result as AnyhowResult};
 use chrono::{DateTime, Utc};
 use ethers::types::U256;
+use once_cell::sync::Lazy;
+use prometheus::{register_int_counter_vec, IntCounterVec};
 use ruint::Uint;
 use semaphore::merkle_tree::Proof;
 use semaphore::poseidon_tree::Branch;
@@ -14,11 +16,13 @@ use tracing::{debug, error, info, instrument, warn};
 use crate::contracts::{IdentityManager, SharedIdentityManager};
 use crate::database::Database;
 use crate::ethereum::write::TransactionId;
+use crate::ethereum::Operation;
 use crate::identity_tree::{
     AppliedTreeUpdate, Hash, Intermediate, TreeVersion, TreeVersionReadOps, TreeWithNextVersion,
 };
 use crate::prover::identity::Identity;
-use crate::prover::{Prover, ReadOnlyProver};
+use crate::prover::{ProverPool, ReadOnlyProver};
+use crate::task_monitor::health::TaskHealth;
 use crate::task_monitor::TaskMonitor;
 use crate::utils::index_packing::pack_indices;
 
@@ -26,6 +30,15 @@ use crate::utils::index_packing::pack_indices;
 /// trigger a forced batch insertion.
 const DEBOUNCE_THRESHOLD_SECS: u64 = 1;
 
+static DEFERRED_BATCHES: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "deferred_batches",
+        "Number of times a batch was deferred because the base fee was above the maximum fee.",
+        &["operation"]
+    )
+    .unwrap()
+});
+
 pub struct ProcessIdentities {
     database:                  Arc<Database>,
     identity_manager:          SharedIdentityManager,
@@ -33,6 +46,7 @@ pub struct ProcessIdentities {
     batch_insert_timeout_secs: u64,
     monitored_txs_sender:      mpsc::Sender<TransactionId>,
     wake_up_notify:            Arc<Notify>,
+    health:                    Arc<TaskHealth>,
 }
 
 impl ProcessIdentities {
@@ -43,6 +57,7 @@ impl ProcessIdentities {
         batch_insert_timeout_secs: u64,
         monitored_txs_sender: mpsc::Sender<TransactionId>,
         wake_up_notify: Arc<Notify>,
+        health: Arc<TaskHealth>,
     ) -> Arc<Self> {
         Arc::new(Self {
             database,
@@ -51,6 +66,7 @@ impl ProcessIdentities {
             batch_insert_timeout_secs,
             monitored_txs_sender,
             wake_up_notify,
+            health,
         })
     }
 
@@ -62,6 +78,7 @@ impl ProcessIdentities {
             &self.monitored_txs_sender,
             &self.wake_up_notify,
             self.batch_insert_timeout_secs,
+            &self.health,
         )
         .await
     }
@@ -74,9 +91,10 @@ async fn process_identities(
     monitored_txs_sender: &mpsc::Sender<TransactionId>,
     wake_up_notify: &Notify,
     timeout_secs: u64,
+    health: &TaskHealth,
 ) -> AnyhowResult<()> {
     info!("Awaiting for a clean slate");
-    identity_manager.await_clean_slate().await?;
+    identity_manager.await_clean_slate(database).await?;
 
     info!("Starting identity processor.");
 
@@ -99,6 +117,8 @@ async fn process_identities(
         .unwrap_or(Utc::now());
 
     loop {
+        health.heartbeat();
+
         // We ping-pong between two cases for being woken. This ensures that there is a
         // maximum time that users can wait for their identity commitment to be
         // processed, but also that we are not inefficient with on-chain gas by being
@@ -106,6 +126,7 @@ async fn process_identities(
         select! {
             _ = timer.tick() => {
                 debug!("Identity batch insertion woken due to timeout.");
+                health.wait_until_resumed().await;
 
                 // If the timer has fired we want to insert whatever
                 // identities we have, even if it's not many. This ensures
@@ -123,7 +144,7 @@ async fn process_identities(
 
                 let updates = batching_tree.peek_next_updates(batch_size);
 
-                commit_identities(
+                let committed = commit_identities(
                     database,
                     identity_manager,
                     batching_tree,
@@ -131,6 +152,11 @@ async fn process_identities(
                     &updates,
                 ).await?;
 
+                // Deferred batches are retried on the next tick
+                if !committed {
+                    continue;
+                }
+
                 last_batch_time = Utc::now();
                 database.update_latest_insertion_timestamp(last_batch_time).await?;
 
@@ -139,6 +165,7 @@ async fn process_identities(
             }
             () = wake_up_notify.notified() => {
                 tracing::trace!("Identity batch insertion woken due to request.");
+                health.wait_until_resumed().await;
 
                 // Capture the time difference since the last batch, and compute
                 // whether we want to insert anyway. We do this if the difference
@@ -183,7 +210,7 @@ async fn process_identities(
                     continue;
                 }
 
-                commit_identities(
+                let committed = commit_identities(
                     database,
                     identity_manager,
                     batching_tree,
@@ -191,6 +218,10 @@ async fn process_identities(
                     &updates,
                 ).await?;
 
+                if !committed {
+                    continue;
+                }
+
                 // We've inserted the identities, so we want to ensure that
                 // we don't trigger again until either we get a full batch
                 // or the timer ticks.
@@ -205,21 +236,42 @@ async fn process_identities(
     }
 }
 
+/// Sends the batch of `updates`, returning whether it was sent or deferred
+/// until fees drop.
 async fn commit_identities(
     database: &Database,
     identity_manager: &IdentityManager,
     batching_tree: &TreeVersion<Intermediate>,
     monitored_txs_sender: &mpsc::Sender<TransactionId>,
     updates: &[AppliedTreeUpdate],
-) -> AnyhowResult<()> {
+) -> AnyhowResult<bool> {
     // If the update is an insertion
-    let tx_id = if updates
+    let operation = if updates
         .first()
         .context("Updates should be > 1")?
         .update
         .element
         != Hash::ZERO
     {
+        Operation::Insertion
+    } else {
+        Operation::Deletion
+    };
+
+    if identity_manager.should_defer(operation).await? {
+        info!(
+            operation = operation.as_str(),
+            "Base fee is above the maximum fee. Deferring batch with {} updates.",
+            updates.len()
+        );
+        DEFERRED_BATCHES
+            .with_label_values(&[operation.as_str()])
+            .inc();
+
+        return Ok(false);
+    }
+
+    let tx_id = if operation == Operation::Insertion {
         let prover = identity_manager
             .get_suitable_insertion_prover(updates.len())
             .await?;
@@ -249,7 +301,7 @@ async fn commit_identities(
         monitored_txs_sender.send(tx_id).await?;
     }
 
-    Ok(())
+    Ok(true)
 }
 
 #[instrument(level = "info", skip_all)]
@@ -258,7 +310,7 @@ pub async fn insert_identities(
     identity_manager: &IdentityManager,
     batching_tree: &TreeVersion<Intermediate>,
     updates: &[AppliedTreeUpdate],
-    prover: ReadOnlyProver<'_, Prover>,
+    prover: ReadOnlyProver<'_, ProverPool>,
 ) -> AnyhowResult<Option<TransactionId>> {
     TaskMonitor::log_identities_queues(database).await?;
 
@@ -347,6 +399,17 @@ pub async fn insert_identities(
             .leaf_index
             + 1;
         let padding = batch_size - commitment_count;
+
+        // A larger prover may have been selected if the optimal one is unhealthy, in
+        // which case the padding must still fit in the tree.
+        let capacity = 1_usize << latest_tree_from_updates.depth();
+        if start_index + padding > capacity {
+            return Err(anyhow::anyhow!(
+                "Batch of {commitment_count} identities padded to {batch_size} does not fit in \
+                 the tree."
+            ));
+        }
+
         commitments.append(&mut vec![U256::zero(); padding]);
 
         for i in start_index..(start_index + padding) {
@@ -389,6 +452,7 @@ pub async fn insert_identities(
 
     // We prepare the proof before reserving a slot in the pending identities
     let proof = IdentityManager::prepare_insertion_proof(
+        database,
         prover,
         start_index,
         pre_root,
@@ -408,6 +472,7 @@ pub async fn insert_identities(
     // identity manager and wait for that transaction to be mined.
     let transaction_id = identity_manager
         .register_identities(
+            database,
             start_index,
             pre_root,
             post_root,
@@ -443,7 +508,7 @@ pub async fn delete_identities(
     identity_manager: &IdentityManager,
     batching_tree: &TreeVersion<Intermediate>,
     updates: &[AppliedTreeUpdate],
-    prover: ReadOnlyProver<'_, Prover>,
+    prover: ReadOnlyProver<'_, ProverPool>,
 ) -> AnyhowResult<Option<TransactionId>> {
     TaskMonitor::log_identities_queues(database).await?;
 
@@ -546,6 +611,7 @@ pub async fn delete_identities(
 
     // We prepare the proof before reserving a slot in the pending identities
     let proof = IdentityManager::prepare_deletion_proof(
+        database,
         prover,
         pre_root,
         deletion_indices.clone(),
@@ -561,7 +627,13 @@ pub async fn delete_identities(
     // With all the data prepared we can submit the identities to the on-chain
     // identity manager and wait for that transaction to be mined.
     let transaction_id = identity_manager
-        .delete_identities(proof, packed_deletion_indices, pre_root, post_root)
+        .delete_identities(
+            database,
+            proof,
+            packed_deletion_indices,
+            pre_root,
+            post_root,
+        )
         .await
         .map_err(|e| {
             error!(?e, "Failed to insert identity to contract.");
@@ -1,4 +1,5 @@
 use std::future::Future;
+use std::sync::Arc;
 use std::time::Duration;
 
 use anyhow::{Error as EyreError, Result as AnyhowResult};
@@ -8,6 +9,8 @@ use tokio::sync::broadcast;
 use tokio::task::JoinHandle;
 use tracing::{error, info};
 
+use crate::task_monitor::health::TaskHealth;
+
 pub mod index_packing;
 pub mod tree_updates;
 
@@ -43,6 +46,7 @@ pub fn spawn_monitored_with_backoff<S, F>(
     future_spawner: S,
     shutdown_sender: broadcast::Sender<()>,
     backoff_duration: Duration,
+    health: Arc<TaskHealth>,
 ) -> JoinHandle<()>
 where
     F: Future<Output = AnyhowResult<()>> + Send + 'static,
@@ -54,6 +58,7 @@ where
             let mut shutdown_receiver = shutdown_sender.subscribe();
 
             let future = future_spawner();
+            health.running();
 
             // Wrap in `AssertUnwindSafe` so we can call `FuturesExt::catch_unwind` on it.
             let future = std::panic::AssertUnwindSafe(future);
@@ -64,6 +69,7 @@ where
                 }
                 _ = shutdown_receiver.recv() => {
                     info!("Woke up by shutdown signal, exiting.");
+                    health.stopped();
                     return;
                 }
             };
@@ -72,9 +78,13 @@ where
 
             match result {
                 // Task succeeded or is shutting down gracefully
-                Ok(Ok(t)) => return t,
+                Ok(Ok(t)) => {
+                    health.stopped();
+                    return t;
+                }
                 Ok(Err(e)) => {
                     error!("Task failed: {e:?}");
+                    health.failed(format!("{e:?}"));
 
                     if cli_batteries::is_shutting_down() {
                         std::process::abort();
@@ -85,6 +95,13 @@ where
                 Err(e) => {
                     error!("Task panicked: {e:?}");
 
+                    let message = e
+                        .downcast_ref::<&str>()
+                        .map(ToString::to_string)
+                        .or_else(|| e.downcast_ref::<String>().cloned())
+                        .unwrap_or_else(|| "unknown panic payload".to_string());
+                    health.failed(format!("Task panicked: {message}"));
+
                     if cli_batteries::is_shutting_down() {
                         std::process::abort();
                     }
@@ -103,6 +120,7 @@ mod tests {
     use std::time::Duration;
 
     use super::*;
+    use crate::task_monitor::health::{TaskKind, TaskState};
 
     #[tokio::test]
     async fn spawn_monitored_test() -> anyhow::Result<()> {
@@ -110,6 +128,7 @@ mod tests {
 
         let can_finish = Arc::new(AtomicBool::new(false));
         let triggered_error = Arc::new(AtomicBool::new(false));
+        let health = Arc::new(TaskHealth::new(TaskKind::MonitorTxs, None));
 
         let handle = {
             let can_finish = can_finish.clone();
@@ -137,6 +156,7 @@ mod tests {
                 },
                 shutdown_sender,
                 Duration::from_secs_f32(0.2),
+                health.clone(),
             )
         };
 
@@ -148,11 +168,19 @@ mod tests {
         assert!(has_triggered_error);
         assert!(!handle.is_finished(), "Task should not be finished");
 
+        let report = health.report();
+        assert!(report.restart_count > 0);
+        assert_eq!(
+            report.last_error.as_deref(),
+            Some("Task panicked: Panicking!")
+        );
+
         can_finish.store(true, Ordering::SeqCst);
         triggered_error.store(false, Ordering::SeqCst);
 
         println!("Waiting for task to finish");
         drop(tokio::time::timeout(Duration::from_secs(1), handle).await?);
+        assert_eq!(health.report().state, TaskState::Stopped);
 
         let has_triggered_error = triggered_error.load(Ordering::SeqCst);
         // There is no code path that allows as to store false on the triggered error
@@ -0,0 +1,203 @@
+mod common;
+
+use common::prelude::*;
+use hyper::StatusCode;
+
+use crate::common::{
+    construct_scheduled_insert_identity_body, test_cancel_deletion, test_cancel_insertion,
+    test_delete_identity,
+};
+
+const SUPPORTED_DEPTH: usize = 18;
+const IDLE_TIME: u64 = 7;
+
+#[tokio::test]
+async fn cancel_queued_operations() -> anyhow::Result<()> {
+    // Initialize logging for the test.
+    init_tracing_subscriber();
+    info!("Starting integration test");
+
+    let insertion_batch_size: usize = 8;
+    let deletion_batch_size: usize = 3;
+
+    #[allow(clippy::cast_possible_truncation)]
+    let tree_depth: u8 = SUPPORTED_DEPTH as u8;
+
+    let mut ref_tree = PoseidonTree::new(SUPPORTED_DEPTH + 1, ruint::Uint::ZERO);
+    let initial_root: U256 = ref_tree.root().into();
+
+    let (mock_chain, db_container, insertion_prover_map, deletion_prover_map, micro_oz) =
+        spawn_deps(
+            initial_root,
+            &[insertion_batch_size],
+            &[deletion_batch_size],
+            tree_depth,
+        )
+        .await?;
+
+    let mock_insertion_prover = &insertion_prover_map[&insertion_batch_size];
+    let mock_deletion_prover = &deletion_prover_map[&deletion_batch_size];
+
+    let db_socket_addr = db_container.address();
+    let db_url = format!("postgres://postgres:postgres@{db_socket_addr}/database");
+
+    // Use a long deletion timeout so that a single queued deletion stays in the
+    // queue for the duration of the test
+    let mut options = Options::try_parse_from([
+        "signup-sequencer",
+        "--identity-manager-address",
+        "0x0000000000000000000000000000000000000000", // placeholder, updated below
+        "--database",
+        &db_url,
+        "--database-max-connections",
+        "1",
+        "--tree-depth",
+        &format!("{tree_depth}"),
+        "--prover-urls",
+        &format!(
+            "[{}, {}]",
+            mock_insertion_prover.arg_string_single(),
+            mock_deletion_prover.arg_string_single()
+        ),
+        "--batch-timeout-seconds",
+        "10",
+        "--batch-deletion-timeout-seconds",
+        "3600",
+        "--min-batch-deletion-size",
+        &format!("{deletion_batch_size}"),
+        "--dense-tree-prefix-depth",
+        "10",
+        "--tree-gc-threshold",
+        "1",
+        "--oz-api-key",
+        "",
+        "--oz-api-secret",
+        "",
+        "--oz-api-url",
+        &micro_oz.endpoint(),
+        "--oz-address",
+        &format!("{:?}", micro_oz.address()),
+    ])
+    .context("Failed to create options")?;
+
+    options.server.server = Url::parse("http://127.0.0.1:0/").expect("Failed to parse URL");
+
+    options.app.contracts.identity_manager_address = mock_chain.identity_manager.address();
+    options.app.ethereum.ethereum_provider =
+        vec![Url::parse(&mock_chain.anvil.endpoint()).expect("Failed to parse Anvil url")];
+
+    let (app, local_addr) = spawn_app(options.clone())
+        .await
+        .expect("Failed to spawn app.");
+
+    let test_identities = generate_test_identities(insertion_batch_size + 1);
+    let identities_ref: Vec<Field> = test_identities
+        .iter()
+        .map(|i| Hash::from_str_radix(i, 16).unwrap())
+        .collect();
+
+    let uri = "http://".to_owned() + &local_addr.to_string();
+    let client = Client::new();
+
+    // Schedule an insertion far in the future so that it stays queued
+    let scheduled_identity = identities_ref[insertion_batch_size];
+    let req = Request::builder()
+        .method("POST")
+        .uri(uri.clone() + "/insertIdentity")
+        .header("Content-Type", "application/json")
+        .body(construct_scheduled_insert_identity_body(
+            &scheduled_identity,
+            "2100-01-01T00:00:00Z",
+        ))
+        .expect("Failed to create insert identity hyper::Body");
+    let response = client
+        .request(req)
+        .await
+        .expect("Failed to execute request.");
+    assert!(response.status().is_success());
+
+    // The queued insertion can be cancelled exactly once
+    test_cancel_insertion(&uri, &client, &scheduled_identity, None).await;
+    test_cancel_insertion(
+        &uri,
+        &client,
+        &scheduled_identity,
+        Some(StatusCode::BAD_REQUEST),
+    )
+    .await;
+
+    // Insert enough identities to trigger an batch to be sent to the blockchain.
+    for i in 0..insertion_batch_size {
+        test_insert_identity(&uri, &client, &mut ref_tree, &identities_ref, i).await;
+    }
+
+    tokio::time::sleep(Duration::from_secs(IDLE_TIME)).await;
+
+    for i in 0..insertion_batch_size {
+        test_inclusion_proof(&uri, &client, i, &ref_tree, &identities_ref[i], false).await;
+    }
+
+    // Insertions that are already in the tree can not be cancelled
+    test_cancel_insertion(
+        &uri,
+        &client,
+        &identities_ref[0],
+        Some(StatusCode::CONFLICT),
+    )
+    .await;
+
+    // Deleting an identity that is not queued for deletion can not be cancelled
+    test_cancel_deletion(
+        &uri,
+        &client,
+        &identities_ref[0],
+        Some(StatusCode::BAD_REQUEST),
+    )
+    .await;
+
+    // Queue a deletion and cancel it before it is applied to the tree
+    let mut deleted_tree = PoseidonTree::new(SUPPORTED_DEPTH + 1, ruint::Uint::ZERO);
+    test_delete_identity(&uri, &client, &mut deleted_tree, &identities_ref, 0, false).await;
+    test_cancel_deletion(&uri, &client, &identities_ref[0], None).await;
+    test_cancel_deletion(
+        &uri,
+        &client,
+        &identities_ref[0],
+        Some(StatusCode::BAD_REQUEST),
+    )
+    .await;
+
+    tokio::time::sleep(Duration::from_secs(IDLE_TIME)).await;
+
+    // The identity is still part of the tree
+    test_inclusion_proof(&uri, &client, 0, &ref_tree, &identities_ref[0], false).await;
+
+    // A full deletion batch is applied to the tree right away, after which its
+    // deletions can not be cancelled anymore
+    for i in 1..=deletion_batch_size {
+        test_delete_identity(&uri, &client, &mut ref_tree, &identities_ref, i, false).await;
+    }
+
+    tokio::time::sleep(Duration::from_secs(IDLE_TIME)).await;
+
+    test_cancel_deletion(
+        &uri,
+        &client,
+        &identities_ref[1],
+        Some(StatusCode::CONFLICT),
+    )
+    .await;
+
+    // Shutdown the app properly for the final time
+    shutdown();
+    app.await.unwrap();
+    for (_, prover) in insertion_prover_map.into_iter() {
+        prover.stop();
+    }
+    for (_, prover) in deletion_prover_map.into_iter() {
+        prover.stop();
+    }
+    reset_shutdown();
+
+    Ok(())
+}
@@ -363,6 +363,72 @@ pub async fn test_delete_identity(
     (ref_tree.proof(leaf_index).unwrap(), ref_tree.root())
 }
 
+#[instrument(skip_all)]
+pub async fn test_cancel_insertion(
+    uri: &str,
+    client: &Client<HttpConnector>,
+    identity_commitment: &Hash,
+    expected_error: Option<StatusCode>,
+) {
+    test_cancel_queued_operation(
+        uri,
+        client,
+        "/cancelInsertion",
+        identity_commitment,
+        expected_error,
+    )
+    .await;
+}
+
+#[instrument(skip_all)]
+pub async fn test_cancel_deletion(
+    uri: &str,
+    client: &Client<HttpConnector>,
+    identity_commitment: &Hash,
+    expected_error: Option<StatusCode>,
+) {
+    test_cancel_queued_operation(
+        uri,
+        client,
+        "/cancelDeletion",
+        identity_commitment,
+        expected_error,
+    )
+    .await;
+}
+
+async fn test_cancel_queued_operation(
+    uri: &str,
+    client: &Client<HttpConnector>,
+    path: &str,
+    identity_commitment: &Hash,
+    expected_error: Option<StatusCode>,
+) {
+    let body = construct_delete_identity_body(identity_commitment);
+
+    let req = Request::builder()
+        .method("POST")
+        .uri(uri.to_owned() + path)
+        .header("Content-Type", "application/json")
+        .body(body)
+        .expect("Failed to create cancel hyper::Body");
+
+    let mut response = client
+        .request(req)
+        .await
+        .expect("Failed to execute request.");
+    let bytes = hyper::body::to_bytes(response.body_mut())
+        .await
+        .expect("Failed to convert response body to bytes");
+
+    if let Some(expected_error) = expected_error {
+        assert_eq!(response.status(), expected_error);
+    } else {
+        assert!(response.status().is_success());
+        assert!(bytes.is_empty());
+    }
+}
+
 #[instrument(skip_all)]
 pub async fn test_recover_identity(
     uri: &str,
@@ -422,6 +488,19 @@ pub async fn test_add_batch_size(
     prover_type: ProverType,
     client: &Client<HttpConnector>,
 ) -> anyhow::Result<()> {
+    try_add_batch_size(uri, prover_url, batch_size, prover_type, client).await;
+
+    Ok(())
+}
+
+/// Sends an `/addBatchSize` request and returns the status of the response.
+pub async fn try_add_batch_size(
+    uri: impl Into<String>,
+    prover_url: impl Into<String>,
+    batch_size: u64,
+    prover_type: ProverType,
+    client: &Client<HttpConnector>,
+) -> StatusCode {
     let prover_url_string: String = prover_url.into();
 
     let body = Body::from(
@@ -443,9 +522,8 @@ pub async fn test_add_batch_size(
     client
         .request(request)
         .await
-        .expect("Failed to execute request.");
-
-    Ok(())
+        .expect("Failed to execute request.")
+        .status()
 }
 
 #[instrument(skip_all)]
@@ -547,6 +625,19 @@ pub fn construct_recover_identity_body(
     )
 }
 
+pub fn construct_scheduled_insert_identity_body(
+    identity_commitment: &Field,
+    not_before: &str,
+) -> Body {
+    Body::from(
+        json!({
+            "identityCommitment": identity_commitment,
+            "notBefore": not_before,
+        })
+        .to_string(),
+    )
+}
+
 pub fn construct_insert_identity_body(identity_commitment: &Field) -> Body {
     Body::from(
         json!({
@@ -5,7 +5,7 @@ use std::sync::Arc;
 
 use anyhow::Context;
 use axum::extract::State;
-use axum::routing::post;
+use axum::routing::{get, post};
 use axum::{Json, Router};
 use axum_server::Handle;
 use ethers::types::U256;
@@ -137,6 +137,17 @@ impl std::fmt::Display for ProverType {
 struct Prover {
     is_available: bool,
     tree_depth:   u8,
+    batch_size:   usize,
+    prover_type:  ProverType,
+}
+
+/// The circuit parameters reported by the `/info` endpoint.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProverInfo {
+    batch_size:  usize,
+    tree_depth:  u8,
+    prover_type: ProverType,
 }
 
 impl ProverService {
@@ -177,13 +188,28 @@ impl ProverService {
             Err(StatusCode::BAD_REQUEST)
         }
 
+        async fn info(state: State<Arc<Mutex<Prover>>>) -> Json<ProverInfo> {
+            let state = state.lock().await;
+
+            Json(ProverInfo {
+                batch_size:  state.batch_size,
+                tree_depth:  state.tree_depth,
+                prover_type: state.prover_type,
+            })
+        }
+
         let inner = Arc::new(Mutex::new(Prover {
             is_available: true,
             tree_depth,
+            batch_size,
+            prover_type,
         }));
         let state = inner.clone();
 
-        let app = Router::new().route("/prove", post(prove)).with_state(state);
+        let app = Router::new()
+            .route("/prove", post(prove))
+            .route("/info", get(info))
+            .with_state(state);
 
         // We use a random port here so that we can run multiple tests in many
         // threads/tasks
@@ -0,0 +1,114 @@
+mod common;
+
+use common::prelude::*;
+
+const SUPPORTED_DEPTH: usize = 20;
+
+/// Sends a batch on top of another one that hasn't been mined yet, which must
+/// not be rejected by the simulation of the second batch.
+#[tokio::test]
+async fn consecutive_batches() -> anyhow::Result<()> {
+    // Initialize logging for the test.
+    init_tracing_subscriber();
+    info!("Starting integration test");
+
+    let batch_size: usize = 3;
+    #[allow(clippy::cast_possible_truncation)]
+    let tree_depth: u8 = SUPPORTED_DEPTH as u8;
+
+    let mut ref_tree = PoseidonTree::new(SUPPORTED_DEPTH + 1, ruint::Uint::ZERO);
+    let initial_root: U256 = ref_tree.root().into();
+
+    let (mock_chain, db_container, insertion_prover_map, _, micro_oz) =
+        spawn_deps(initial_root, &[batch_size], &[], tree_depth).await?;
+
+    let prover_mock = &insertion_prover_map[&batch_size];
+
+    let db_socket_addr = db_container.address();
+    let db_url = format!("postgres://postgres:postgres@{db_socket_addr}/database");
+
+    let mut options = Options::try_parse_from([
+        "signup-sequencer",
+        "--identity-manager-address",
+        "0x0000000000000000000000000000000000000000", // placeholder, updated below
+        "--database",
+        &db_url,
+        "--database-max-connections",
+        "1",
+        "--tree-depth",
+        &format!("{tree_depth}"),
+        "--prover-urls",
+        &prover_mock.arg_string(),
+        "--batch-timeout-seconds",
+        "10",
+        "--dense-tree-prefix-depth",
+        "10",
+        "--tree-gc-threshold",
+        "1",
+        "--oz-api-key",
+        "",
+        "--oz-api-secret",
+        "",
+        "--oz-api-url",
+        &micro_oz.endpoint(),
+        "--oz-address",
+        &format!("{:?}", micro_oz.address()),
+        "--time-between-scans-seconds",
+        "1",
+    ])
+    .context("Failed to create options")?;
+
+    options.server.server = Url::parse("http://127.0.0.1:0/").expect("Failed to parse URL");
+
+    options.app.contracts.identity_manager_address = mock_chain.identity_manager.address();
+    options.app.ethereum.ethereum_provider =
+        vec![Url::parse(&mock_chain.anvil.endpoint()).expect("Failed to parse Anvil url")];
+
+    let (app, local_addr) = spawn_app(options.clone())
+        .await
+        .expect("Failed to spawn app.");
+
+    let test_identities = generate_test_identities(batch_size * 2);
+    let identities_ref: Vec<Field> = test_identities
+        .iter()
+        .map(|i| Hash::from_str_radix(i, 16).unwrap())
+        .collect();
+
+    let uri = "http://".to_owned() + &local_addr.to_string();
+    let client = Client::new();
+
+    // Both batches are sent within the same block, so the second one is built
+    // on top of the unmined post root of the first one
+    for i in 0..batch_size * 2 {
+        test_insert_identity(&uri, &client, &mut ref_tree, &identities_ref, i).await;
+    }
+
+    for (i, identity) in identities_ref.iter().enumerate() {
+        test_inclusion_proof(&uri, &client, i, &ref_tree, identity, false).await;
+    }
+
+    // The processing task must not have been restarted by a failed simulation
+    let response = client
+        .get(format!("{uri}/health/ready").parse()?)
+        .await
+        .expect("Failed to execute request.");
+    let bytes = hyper::body::to_bytes(response.into_body()).await?;
+    let health: serde_json::Value = serde_json::from_slice(&bytes)?;
+    let process_identities = health["tasks"]
+        .as_array()
+        .expect("Failed to get tasks")
+        .iter()
+        .find(|task| task["name"] == "process_identities")
+        .expect("Failed to find the process identities task");
+    assert_eq!(process_identities["restartCount"], 0);
+
+    // Shutdown the app properly for the final time
+    shutdown();
+    app.await.unwrap();
+    for (_, prover) in insertion_prover_map.into_iter() {
+        prover.stop();
+    }
+    reset_shutdown();
+
+    Ok(())
+}
@@ -0,0 +1,112 @@
+mod common;
+
+use std::net::{Ipv4Addr, SocketAddr};
+
+use common::prelude::*;
+use dev_prover::{ProverInfo, ProverType};
+
+const SUPPORTED_DEPTH: usize = 20;
+
+/// Inserts a batch of identities using the dev prover, which rejects any tree
+/// transition the sequencer gets wrong.
+#[tokio::test]
+async fn dev_prover() -> anyhow::Result<()> {
+    // Initialize logging for the test.
+    init_tracing_subscriber();
+    info!("Starting integration test");
+
+    let batch_size: usize = 3;
+    #[allow(clippy::cast_possible_truncation)]
+    let tree_depth: u8 = SUPPORTED_DEPTH as u8;
+
+    let mut ref_tree = PoseidonTree::new(SUPPORTED_DEPTH + 1, ruint::Uint::ZERO);
+    let initial_root: U256 = ref_tree.root().into();
+
+    let (mock_chain, db_container, insertion_prover_map, _, micro_oz) =
+        spawn_deps(initial_root, &[batch_size], &[], tree_depth).await?;
+
+    let dev_prover =
+        dev_prover::spawn(SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0), ProverInfo {
+            batch_size,
+            tree_depth: SUPPORTED_DEPTH,
+            prover_type: ProverType::Insertion,
+        })
+        .await?;
+    let prover_urls = format!(
+        r#"[{{"url": "{}","batch_size": {batch_size},"timeout_s": 30, "prover_type": "insertion"}}]"#,
+        dev_prover.endpoint()
+    );
+
+    let db_socket_addr = db_container.address();
+    let db_url = format!("postgres://postgres:postgres@{db_socket_addr}/database");
+
+    let mut options = Options::try_parse_from([
+        "signup-sequencer",
+        "--identity-manager-address",
+        "0x0000000000000000000000000000000000000000", // placeholder, updated below
+        "--database",
+        &db_url,
+        "--database-max-connections",
+        "1",
+        "--tree-depth",
+        &format!("{tree_depth}"),
+        "--prover-urls",
+        &prover_urls,
+        "--batch-timeout-seconds",
+        "10",
+        "--dense-tree-prefix-depth",
+        "10",
+        "--tree-gc-threshold",
+        "1",
+        "--oz-api-key",
+        "",
+        "--oz-api-secret",
+        "",
+        "--oz-api-url",
+        &micro_oz.endpoint(),
+        "--oz-address",
+        &format!("{:?}", micro_oz.address()),
+        "--time-between-scans-seconds",
+        "1",
+    ])
+    .context("Failed to create options")?;
+
+    options.server.server = Url::parse("http://127.0.0.1:0/").expect("Failed to parse URL");
+
+    options.app.contracts.identity_manager_address = mock_chain.identity_manager.address();
+    options.app.ethereum.ethereum_provider =
+        vec![Url::parse(&mock_chain.anvil.endpoint()).expect("Failed to parse Anvil url")];
+
+    let (app, local_addr) = spawn_app(options.clone())
+        .await
+        .expect("Failed to spawn app.");
+
+    let test_identities = generate_test_identities(batch_size);
+    let identities_ref: Vec<Field> = test_identities
+        .iter()
+        .map(|i| Hash::from_str_radix(i, 16).unwrap())
+        .collect();
+
+    let uri = "http://".to_owned() + &local_addr.to_string();
+    let client = Client::new();
+
+    for i in 0..batch_size {
+        test_insert_identity(&uri, &client, &mut ref_tree, &identities_ref, i).await;
+    }
+
+    // The identities are only mined if the dev prover accepted the batch
+    for (i, identity) in identities_ref.iter().enumerate() {
+        test_inclusion_proof(&uri, &client, i, &ref_tree, identity, false).await;
+    }
+
+    // Shutdown the app properly for the final time
+    shutdown();
+    app.await.unwrap();
+    dev_prover.shutdown().await;
+    for (_, prover) in insertion_prover_map.into_iter() {
+        prover.stop();
+    }
+    reset_shutdown();
+
+    Ok(())
+}
@@ -3,9 +3,9 @@ mod common;
 use std::str::FromStr;
 
 use common::prelude::*;
-use hyper::Uri;
+use hyper::{StatusCode, Uri};
 
-use crate::common::{test_add_batch_size, test_remove_batch_size};
+use crate::common::{test_add_batch_size, test_remove_batch_size, try_add_batch_size};
 
 const SUPPORTED_DEPTH: usize = 20;
 const IDLE_TIME: u64 = 10;
@@ -76,7 +76,7 @@ async fn dynamic_batch_sizes() -> anyhow::Result<()> {
 
     options.app.contracts.identity_manager_address = mock_chain.identity_manager.address();
     options.app.ethereum.ethereum_provider =
-        Url::parse(&mock_chain.anvil.endpoint()).expect("Failed to parse Anvil url");
+        vec![Url::parse(&mock_chain.anvil.endpoint()).expect("Failed to parse Anvil url")];
 
     let (app, local_addr) = spawn_app(options.clone())
         .await
@@ -132,6 +132,18 @@ async fn dynamic_batch_sizes() -> anyhow::Result<()> {
     )
     .await;
 
+    // Provers that report different circuit parameters than configured are
+    // rejected.
+    let status = try_add_batch_size(
+        &uri,
+        second_prover.url(),
+        first_batch_size as u64,
+        second_prover.prover_type(),
+        &client,
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
     test_add_batch_size(
         &uri,
         second_prover.url(),
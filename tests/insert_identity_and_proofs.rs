@@ -61,7 +61,7 @@ async fn insert_identity_and_proofs() -> anyhow::Result<()> {
 
     options.app.contracts.identity_manager_address = mock_chain.identity_manager.address();
     options.app.ethereum.ethereum_provider =
-        Url::parse(&mock_chain.anvil.endpoint()).expect("Failed to parse Anvil url");
+        vec![Url::parse(&mock_chain.anvil.endpoint()).expect("Failed to parse Anvil url")];
 
     let (app, local_addr) = spawn_app(options.clone())
         .await
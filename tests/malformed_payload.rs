@@ -58,7 +58,7 @@ async fn malformed_payload() -> anyhow::Result<()> {
     options.server.server = Url::parse("http://127.0.0.1:0/")?;
 
     options.app.contracts.identity_manager_address = mock_chain.identity_manager.address();
-    options.app.ethereum.ethereum_provider = Url::parse(&mock_chain.anvil.endpoint())?;
+    options.app.ethereum.ethereum_provider = vec![Url::parse(&mock_chain.anvil.endpoint())?];
 
     let (app, local_addr) = spawn_app(options.clone())
         .await
@@ -0,0 +1,93 @@
+mod common;
+
+use common::prelude::*;
+
+/// Tests that batches are submitted when transactions are signed by a remote
+/// signer instead of being relayed.
+#[tokio::test]
+async fn remote_signer() -> anyhow::Result<()> {
+    init_tracing_subscriber();
+    info!("Starting remote signer test");
+
+    let tree_depth: u8 = 20;
+
+    let mut ref_tree = PoseidonTree::new(tree_depth as usize + 1, ruint::Uint::ZERO);
+    let initial_root: U256 = ref_tree.root().into();
+
+    let batch_size: usize = 3;
+
+    let (mock_chain, db_container, insertion_prover_map, _, _micro_oz) =
+        spawn_deps(initial_root, &[batch_size], &[], tree_depth).await?;
+
+    let signing_key = SigningKey::from_bytes(mock_chain.private_key.as_bytes())?;
+    let micro_signer = micro_signer::spawn(signing_key).await?;
+
+    let prover_mock = &insertion_prover_map[&batch_size];
+
+    let db_socket_addr = db_container.address();
+    let db_url = format!("postgres://postgres:postgres@{db_socket_addr}/database");
+    let mut options = Options::try_parse_from([
+        "signup-sequencer",
+        "--identity-manager-address",
+        "0x0000000000000000000000000000000000000000", // placeholder, updated below
+        "--database",
+        &db_url,
+        "--database-max-connections",
+        "1",
+        "--tree-depth",
+        &format!("{tree_depth}"),
+        "--prover-urls",
+        &prover_mock.arg_string(),
+        "--batch-timeout-seconds",
+        "10",
+        "--dense-tree-prefix-depth",
+        "10",
+        "--tree-gc-threshold",
+        "1",
+        "--write-provider",
+        "remote",
+        "--remote-signer-url",
+        &micro_signer.endpoint(),
+        "--remote-signer-address",
+        &format!("{:?}", micro_signer.address()),
+        "--time-between-scans-seconds",
+        "1",
+    ])
+    .context("Failed to create options")?;
+
+    options.server.server = Url::parse("http://127.0.0.1:0/")?;
+
+    options.app.contracts.identity_manager_address = mock_chain.identity_manager.address();
+    options.app.ethereum.ethereum_provider = vec![Url::parse(&mock_chain.anvil.endpoint())?];
+
+    let (app, local_addr) = spawn_app(options.clone())
+        .await
+        .expect("Failed to spawn app.");
+
+    let test_identities = generate_test_identities(batch_size);
+    let identities_ref: Vec<Field> = test_identities
+        .iter()
+        .map(|i| Hash::from_str_radix(i, 16).unwrap())
+        .collect();
+
+    let uri = "http://".to_owned() + &local_addr.to_string();
+    let client = Client::new();
+
+    test_insert_identity(&uri, &client, &mut ref_tree, &identities_ref, 0).await;
+    test_insert_identity(&uri, &client, &mut ref_tree, &identities_ref, 1).await;
+    test_insert_identity(&uri, &client, &mut ref_tree, &identities_ref, 2).await;
+
+    test_inclusion_proof(&uri, &client, 0, &ref_tree, &identities_ref[0], false).await;
+    test_inclusion_proof(&uri, &client, 1, &ref_tree, &identities_ref[1], false).await;
+    test_inclusion_proof(&uri, &client, 2, &ref_tree, &identities_ref[2], false).await;
+
+    shutdown();
+    app.await?;
+    for (_, prover) in insertion_prover_map.into_iter() {
+        prover.stop();
+    }
+    micro_signer.shutdown().await;
+    reset_shutdown();
+
+    Ok(())
+}
@@ -63,7 +63,7 @@ async fn validate_proofs() -> anyhow::Result<()> {
 
     options.app.contracts.identity_manager_address = mock_chain.identity_manager.address();
     options.app.ethereum.ethereum_provider =
-        Url::parse(&mock_chain.anvil.endpoint()).expect("Failed to parse ganache endpoint");
+        vec![Url::parse(&mock_chain.anvil.endpoint()).expect("Failed to parse ganache endpoint")];
 
     let (app, local_addr) = spawn_app(options.clone())
         .await